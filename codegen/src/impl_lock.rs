@@ -3,11 +3,12 @@ use proc_macro2::Span;
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::Expr;
+use syn::LitStr;
 use syn::Result;
 
 use crate::parse_args::DistLockArgs;
 
-pub(crate) fn generate(lock_args: DistLockArgs) -> Result<TokenStream> {
+pub(crate) fn generate(lock_args: DistLockArgs, is_async: bool) -> Result<TokenStream> {
 	let name = lock_args.name.value();
 	let at_most_string = lock_args.at_most.value();
 	let at_most = at_most_string
@@ -25,94 +26,96 @@ pub(crate) fn generate(lock_args: DistLockArgs) -> Result<TokenStream> {
 			syn::Error::new(Span::call_site(), format!("can't prase at_least: {}", at_most_string))
 		})?;
 
-	Ok(gen_lock_code(name, at_most, at_least, lock_args.transport))
+	let provider = match lock_args.provider {
+		Some(provider) => provider,
+		None => LitStr::new(default_provider()?, Span::call_site()),
+	};
+
+	gen_lock_code(name, at_most, at_least, lock_args.transport, provider, is_async)
 }
 
-#[cfg(feature = "redis")]
-fn gen_lock_code(
-	name: String,
-	at_most_mills: i64,
-	at_least_mills: i64,
-	transport: Expr,
-) -> TokenStream {
-	let lock_name = Ident::new(&name, Span::call_site());
-	let acquire_expr = acquire_lock_expr(&lock_name);
-	quote! {
-	   let mut #lock_name = {
-			use ::dist_lock::core::DistLock;
-			use ::dist_lock::core::LockConfig;
-			use ::dist_lock::provider::RedisDriver;
+fn default_provider() -> Result<&'static str> {
+	let enabled = [
+		(cfg!(feature = "redis"), "redis"),
+		(cfg!(feature = "diesel"), "diesel"),
+		(cfg!(feature = "zookeeper"), "zookeeper"),
+		(cfg!(feature = "memory"), "memory"),
+		(cfg!(feature = "file"), "file"),
+		(cfg!(feature = "etcd"), "etcd"),
+	]
+	.into_iter()
+	.filter_map(|(enabled, provider)| enabled.then_some(provider))
+	.collect::<Vec<_>>();
 
-			let lock_name = #name.to_string();
-			let driver = RedisDriver::new(&lock_name, #transport);
-			let config = LockConfig::from_mills(lock_name, #at_least_mills, #at_most_mills);
-			DistLock::new(config, driver)
-		};
-		#acquire_expr;
+	match enabled[..] {
+		[provider] => Ok(provider),
+		_ => Err(syn::Error::new(
+			Span::call_site(),
+			"can't infer provider, set one of: provider = \"redis\" | \"diesel\" | \"zookeeper\" | \"memory\" | \"file\" | \"etcd\"",
+		)),
 	}
 }
 
-#[cfg(feature = "diesel")]
 fn gen_lock_code(
 	name: String,
 	at_most_mills: i64,
 	at_least_mills: i64,
 	transport: Expr,
-) -> TokenStream {
-	let lock_name = Ident::new(&name, Span::call_site());
-	let acquire_expr = acquire_lock_expr(&lock_name);
-	quote! {
-	   let mut #lock_name = {
-			use ::dist_lock::core::DistLock;
-			use ::dist_lock::core::LockConfig;
-			use ::dist_lock::provider::DieselDriver;
+	provider: LitStr,
+	is_async: bool,
+) -> Result<TokenStream> {
+	let driver = match provider.value().as_str() {
+		#[cfg(feature = "redis")]
+		"redis" => quote! {
+			::dist_lock::provider::RedisDriver::new(&lock_name, #transport)
+		},
+		#[cfg(feature = "diesel")]
+		"diesel" => quote! {
+			::dist_lock::provider::DieselDriver::new(&lock_name, Some("t"), #transport)
+		},
+		#[cfg(feature = "zookeeper")]
+		"zookeeper" => quote! {
+			::dist_lock::provider::ZookeeperDriver::new(None, #transport)?
+		},
+		#[cfg(feature = "memory")]
+		"memory" => quote! {
+			::dist_lock::provider::MemoryDriver::clone(#transport)
+		},
+		#[cfg(feature = "file")]
+		"file" => quote! {
+			::dist_lock::provider::FileDriver::new(#transport)?
+		},
+		#[cfg(feature = "etcd")]
+		"etcd" => quote! {
+			::dist_lock::provider::EtcdDriver::new(&lock_name, #transport)
+		},
+		other => {
+			return Err(syn::Error::new(
+				provider.span(),
+				format!("provider not enabled: {}", other),
+			))
+		}
+	};
 
-			let lock_name = #name.to_string();
-			let driver = DieselDriver::new(&lock_name, Some("t"), #transport);
-			let config = LockConfig::from_mills(lock_name, #at_least_mills, #at_most_mills);
-			DistLock::new(config, driver)
-		};
-		#acquire_expr;
-	}
-}
+	let (lock_type, await_expr) = if is_async {
+		(quote!(AsyncDistLock), quote!(.await))
+	} else {
+		(quote!(DistLock), quote!())
+	};
 
-#[cfg(feature = "zookeeper")]
-fn gen_lock_code(
-	name: String,
-	at_most_mills: i64,
-	at_least_mills: i64,
-	transport: Expr,
-) -> TokenStream {
 	let lock_name = Ident::new(&name, Span::call_site());
-	let acquire_expr = acquire_lock_expr(&lock_name);
-	quote! {
-	   let mut #lock_name = {
-			use ::dist_lock::core::DistLock;
+	Ok(quote! {
+		let #lock_name = {
+			use ::dist_lock::core::#lock_type;
 			use ::dist_lock::core::LockConfig;
-			use ::dist_lock::provider::ZookeeperDriver;
 
 			let lock_name = #name.to_string();
-
-			let driver = ZookeeperDriver::new(None, #transport)?;
+			let driver = #driver;
 			let config = LockConfig::from_mills(lock_name, #at_least_mills, #at_most_mills);
-			DistLock::new(config, driver)
+			#lock_type::new(config, driver)
 		};
-		#acquire_expr;
-	}
-}
-
-cfg_if::cfg_if! {
-	if #[cfg(feature = "async")] {
-	   fn acquire_lock_expr(name: &Ident) -> TokenStream {
-			quote!{
-				let _ = #name.acquire().await?
-			}
-	   }
-	} else {
-		fn acquire_lock_expr(name: &Ident) -> TokenStream {
-			quote!{
-			  let _ = #name.acquire()?
-			}
-		}
-	}
+		let __dist_lock_guard = #lock_name
+			.try_lock()#await_expr?
+			.ok_or(::dist_lock::error::LockError::LockFailed)?;
+	})
 }
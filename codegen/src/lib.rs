@@ -16,7 +16,12 @@ mod parse_args;
 /// - name: Lock name.
 /// - at_most: Max lock duration.
 /// - at_least: Min lock duration.
-/// - transport: Driver connection.
+/// - transport: Driver connection, a `&MemoryDriver` for the `memory` provider and the lock
+///   directory for the `file` provider.
+/// - provider: `redis`, `diesel`, `zookeeper`, `memory`, `file` or `etcd`, only required if several
+///   are enabled. `etcd` only has an async driver.
+///
+/// An `async fn` gets an `AsyncDistLock`, any other fn a `DistLock`.
 ///
 /// Usage:
 /// ```
@@ -34,7 +39,7 @@ pub fn dist_lock(head: TokenStream, body: TokenStream) -> TokenStream {
 }
 
 fn parse(lock_args: DistLockArgs, function: ItemFn) -> Result<proc_macro2::TokenStream> {
-	let dist_lock = generate(lock_args)?;
+	let dist_lock = generate(lock_args, function.sig.asyncness.is_some())?;
 	let fn_vis = function.vis;
 	let fn_body = function.block;
 	let fn_sig = function.sig;
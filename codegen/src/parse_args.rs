@@ -10,6 +10,7 @@ pub(crate) struct DistLockArgs {
 	pub(crate) at_most: LitStr,
 	pub(crate) at_least: Option<LitStr>,
 	pub(crate) transport: Expr,
+	pub(crate) provider: Option<LitStr>,
 }
 
 impl Parse for DistLockArgs {
@@ -18,6 +19,7 @@ impl Parse for DistLockArgs {
 		let mut at_least = None;
 		let mut at_most = None;
 		let mut transport = None;
+		let mut provider = None;
 		while !input.is_empty() {
 			let lookahead = input.lookahead1();
 			if lookahead.peek(kw::name) {
@@ -37,6 +39,10 @@ impl Parse for DistLockArgs {
 				let content;
 				parenthesized!(content in input);
 				transport = Some(content.parse::<Expr>()?);
+			} else if lookahead.peek(kw::provider) {
+				_ = input.parse::<kw::provider>()?;
+				_ = input.parse::<Token![=]>()?;
+				provider = Some(input.parse::<LitStr>()?);
 			} else if lookahead.peek(Token![,]) {
 				_ = input.parse::<Token![,]>()?;
 			} else {
@@ -50,6 +56,7 @@ impl Parse for DistLockArgs {
 			at_least,
 			transport: transport
 				.ok_or(syn::Error::new(Span::call_site(), "transport not found"))?,
+			provider,
 		})
 	}
 }
@@ -61,4 +68,5 @@ mod kw {
 	custom_keyword!(at_least);
 	custom_keyword!(at_most);
 	custom_keyword!(transport);
+	custom_keyword!(provider);
 }
@@ -0,0 +1,33 @@
+//! Operator tools working on every lock of a backend instead of the lock of one `LockConfig`.
+//!
+//! Locks are reported as `LockInfo` records, and `force_unlock` takes a lock away from its holder,
+//! which only notices on its next extend or release.
+use crate::core::LockInfo;
+use crate::error::LockResult;
+
+macro_rules! impl_lock_admin {
+	($(#[$meta: meta])* $admin: ident, $($async: ident)?) => {
+		$(#[$meta])*
+		pub trait $admin {
+			/// Every live lock stored by the backend, in no particular order.
+			$($async)? fn list_locks(&self) -> LockResult<Vec<LockInfo>>;
+
+			/// Release the lock on `name` no matter who owns it, `true` if it was held.
+			$($async)? fn force_unlock(&self, name: &str) -> LockResult<bool>;
+		}
+	};
+}
+
+impl_lock_admin!(
+	/// Admin operations of a blocking lock provider.
+	LockAdmin,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_lock_admin!(
+	/// Admin operations of an async lock provider, available with the `tokio` or `async-std`
+	/// feature.
+	#[async_trait::async_trait]
+	AsyncLockAdmin,
+	async
+);
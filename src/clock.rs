@@ -0,0 +1,53 @@
+//! Source of the current time for lease bookkeeping.
+//!
+//! Every `LockConfig` carries a `Clock`, used by `DistLock` and the drivers wherever they read the
+//! local time: lock states, `min_lock` holds, lease ends written to the backend. `SystemClock` is
+//! the default, `MockClock` only moves when told to, so expiry can be tested without sleeping.
+//! Backends that expire locks on their own clock, like Redis key TTLs, are not affected.
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use crossbeam_utils::atomic::AtomicCell;
+
+pub trait Clock: Debug + Send + Sync {
+	fn now(&self) -> DateTime<Utc>;
+}
+
+/// The time of this host.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> DateTime<Utc> {
+		Utc::now()
+	}
+}
+
+/// A clock that stands still until it is advanced, clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+	now: Arc<AtomicCell<DateTime<Utc>>>,
+}
+
+impl MockClock {
+	pub fn new(now: DateTime<Utc>) -> MockClock {
+		MockClock { now: Arc::new(AtomicCell::new(now)) }
+	}
+
+	pub fn advance(&self, duration: Duration) {
+		self.now.store(self.now.load() + duration);
+	}
+
+	pub fn set(&self, now: DateTime<Utc>) {
+		self.now.store(now);
+	}
+}
+
+impl Clock for MockClock {
+	fn now(&self) -> DateTime<Utc> {
+		self.now.load()
+	}
+}
@@ -1,49 +1,95 @@
-use cfg_if::cfg_if;
+use std::collections::hash_map::RandomState;
+use std::fmt::Display;
+use std::hash::BuildHasher;
+use std::hash::Hasher;
+use std::process;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Instant;
+
 use chrono::DateTime;
 use chrono::Duration;
 use chrono::Utc;
+use crossbeam_utils::atomic::AtomicCell;
+use gethostname::gethostname;
 
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+use crate::error::ConfigError;
+use crate::error::LockError;
 use crate::error::LockResult;
+use crate::retry::retry;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::retry::retry_async;
+use crate::retry::RetryPolicy;
+use crate::signal::LostReason;
+use crate::signal::LostSignal;
+use crate::telemetry;
+use crate::telemetry::instrument;
+use crate::telemetry::Call;
+use crate::telemetry::Op;
+
+pub(crate) type StateCell = AtomicCell<LockState>;
+
+pub(crate) trait StateOpr {
+	fn set(&self, state: LockState);
+	fn get(&self) -> LockState;
+}
 
-cfg_if! {
-	if #[cfg(any(feature = "tokio", feature = "async-std"))] {
-		type StateCell = crossbeam_utils::atomic::AtomicCell<LockState>;
-
-		trait StateOpr {
-			fn set(&self, state: LockState);
-			fn get(&self) -> LockState;
-		}
-
-		impl StateOpr for StateCell {
-			fn set(&self, state: LockState) {
-				self.store(state);
-			}
-
-			fn get(&self) -> LockState {
-				self.load()
-			}
-		}
-	} else {
-		type StateCell = std::cell::Cell<LockState>;
+impl StateOpr for StateCell {
+	fn set(&self, state: LockState) {
+		self.store(state);
 	}
-}
 
-#[derive(Debug)]
-pub struct DistLock<T: Lockable> {
-	pub(super) config: LockConfig,
-	pub(super) driver: T,
-	pub(super) state: StateCell,
-	pub(super) create_at: DateTime<Utc>,
+	fn get(&self) -> LockState {
+		self.load()
+	}
 }
 
 macro_rules! impl_dist_lock {
 	(
+		$(#[$meta: meta])*
+		$lock: ident,
+		$guard: ident,
+		$lockable: ident,
+		$retry: ident,
+		$reentrant_owner: path,
 		$($async: ident)?,
 		$($await: tt)*
 	) => {
-		impl<T: Lockable> DistLock<T> {
-			pub fn new(config: LockConfig, driver: T) -> Self {
-				DistLock { config, driver, state: Default::default(), create_at: Utc::now() }
+		$(#[$meta])*
+		#[derive(Debug)]
+		pub struct $lock<T: $lockable> {
+			pub(super) config: LockConfig,
+			pub(super) driver: T,
+			pub(super) state: StateCell,
+			pub(super) lost: LostSignal,
+			pub(super) create_at: DateTime<Utc>,
+			pub(super) held_since: AtomicCell<Option<Instant>>,
+		}
+
+		impl<T: $lockable> $lock<T> {
+			pub fn new(mut config: LockConfig, driver: T) -> Self {
+				if config.owner.is_none() {
+					config.owner = Some(match config.reentrant {
+						true => $reentrant_owner(),
+						false => LockOwner::new(),
+					});
+				}
+
+				let lost = LostSignal::new();
+				driver.watch_lost(&lost);
+				let create_at = config.now();
+				$lock {
+					config,
+					driver,
+					state: Default::default(),
+					lost,
+					create_at,
+					held_since: AtomicCell::new(None),
+				}
 			}
 
 			pub fn driver(&self) -> &T {
@@ -58,27 +104,102 @@ macro_rules! impl_dist_lock {
 				self.state.get()
 			}
 
+			/// Signal fired when the held lock is lost, see `LostSignal`.
+			pub fn lost_signal(&self) -> &LostSignal {
+				&self.lost
+			}
+
 			pub fn create_at(&self) -> &DateTime<Utc> {
 				&self.create_at
 			}
 
+			/// Acquire the lock and return a guard that releases it on drop, `None` if the lock is
+			/// held by someone else.
+			pub $($async)? fn try_lock(&self) -> LockResult<Option<$guard<'_, T>>> {
+				if self.acquire()$($await)*? {
+					Ok(Some($guard { lock: self }))
+				} else {
+					Ok(None)
+				}
+			}
+
+			/// Acquire the lock, a reentrant lock goes to the backend every time to add a hold.
 			pub $($async)? fn acquire(&self) -> LockResult<bool> {
-				if self.state.get().check_locked(&self.config) {
+				if !self.config.reentrant && self.state.get().check_locked(&self.config) {
 					return Ok(true);
 				}
 
-				let state = self.driver.acquire_lock(&self.config)$($await)*?;
+				let pending = PendingAcquire { state: &self.state };
+				let call = Call::start(Op::Acquire, &self.config, self.driver.provider());
+				let result = instrument!(call, self.driver.acquire_lock(&self.config), $($await)*);
+				std::mem::forget(pending);
+				call.finish(&self.config, self.driver.provider(), &result);
+
+				let state = result?;
+				if state.is_locked {
+					self.lost.reset();
+					if !self.config.reentrant || state.holds == 1 {
+						self.held_since.store(Some(call.started()));
+					}
+				}
+
 				self.state.set(state);
 				Ok(state.is_locked)
 			}
 
+			/// Retry `acquire` following the retry policy of the config and return a guard that
+			/// releases the lock on drop.
+			pub $($async)? fn lock(&self) -> LockResult<$guard<'_, T>> {
+				self.acquire_with(self.config.retry)$($await)*?;
+				Ok($guard { lock: self })
+			}
+
+			/// Retry `acquire` following the retry policy of the config until the lock is held or
+			/// `LockError::Timeout` once `timeout` has passed.
+			pub $($async)? fn acquire_timeout(&self, timeout: Duration) -> LockResult<()> {
+				self.acquire_with(self.config.retry.timeout(timeout))$($await)*
+			}
+
+			/// Retry `acquire` following `policy`, fails with `LockError::Timeout` once the
+			/// policy timeout has passed or `LockError::LockFailed` once its attempts are used up.
+			/// A fair lock leaves the queue when giving up, the policy delay should stay below its
+			/// queue timeout to keep the place in the queue.
+			///
+			/// The async version is cancellation safe: dropping it while sleeping leaves nothing
+			/// behind, dropping it during a backend call marks the lock as possibly held so that
+			/// `release` or dropping the lock cleans it up.
+			pub $($async)? fn acquire_with(&self, policy: RetryPolicy) -> LockResult<()> {
+				let result = $retry(policy, move || self.acquire())$($await)*;
+				let gave_up = matches!(result, Err(LockError::LockFailed | LockError::Timeout));
+				if gave_up && self.config.is_fair() {
+					self.driver.leave_queue(&self.config)$($await)*?;
+				}
+				result
+			}
+
+			/// Release the lock, a reentrant lock only drops one hold and stays locked until the
+			/// hold count reaches zero.
 			pub $($async)? fn release(&self) -> LockResult<()> {
 				if !self.state.get().is_locked {
 					return Ok(());
 				}
 
-				let state = self.driver.release_lock(&self.config, &self.state.get())$($await)*?;
-				self.state.set(state);
+				let held = self.state.get();
+				let call = Call::start(Op::Release, &self.config, self.driver.provider());
+				let result =
+					instrument!(call, self.driver.release_lock(&self.config, &held), $($await)*);
+				call.finish(&self.config, self.driver.provider(), &result);
+				if matches!(result, Err(LockError::NotOwner(_))) {
+					self.state.set(LockState::unlock());
+					self.lost.fire(LostReason::NotOwner);
+				}
+
+				self.state.set(result?);
+				if !self.config.reentrant || held.holds <= 1 {
+					if let Some(since) = self.held_since.take() {
+						telemetry::record_hold(&self.config, self.driver.provider(), since.elapsed());
+					}
+				}
 				Ok(())
 			}
 
@@ -87,30 +208,193 @@ macro_rules! impl_dist_lock {
 					return Ok(false);
 				}
 
-				let state = self.driver.extend_lock(&self.config)$($await)*?;
+				let call = Call::start(Op::Extend, &self.config, self.driver.provider());
+				let result = instrument!(call, self.driver.extend_lock(&self.config), $($await)*);
+				call.finish(&self.config, self.driver.provider(), &result);
+				if matches!(result, Err(LockError::NotOwner(_))) {
+					self.state.set(LockState::unlock());
+					self.lost.fire(LostReason::NotOwner);
+				}
+
+				let state = result?;
+				if !state.is_locked {
+					self.lost.fire(LostReason::Expired);
+				}
+
 				self.state.set(state);
 				Ok(state.is_locked)
 			}
 		}
+
+		impl<T: $lockable> Drop for $lock<T> {
+			fn drop(&mut self) {
+				self.release_on_drop();
+			}
+		}
+
+		/// Proof of holding a lock, the lock is released through `release_lock` of the driver
+		/// when the guard is dropped.
+		#[derive(Debug)]
+		#[must_use = "if unused the lock will be released immediately"]
+		pub struct $guard<'a, T: $lockable> {
+			lock: &'a $lock<T>,
+		}
+
+		impl<T: $lockable> $guard<'_, T> {
+			pub fn lock(&self) -> &$lock<T> {
+				self.lock
+			}
+
+			pub fn state(&self) -> LockState {
+				self.lock.state()
+			}
+
+			pub fn lost_signal(&self) -> &LostSignal {
+				&self.lock.lost
+			}
+
+			pub fn fencing_token(&self) -> i64 {
+				self.lock.state().token
+			}
+
+			/// Time left until the lease expires, zero once it has.
+			pub fn remaining(&self) -> Duration {
+				let state = self.lock.state();
+				if !state.is_locked {
+					return Duration::zero();
+				}
+
+				let config = &self.lock.config;
+				config
+					.local_until(state.locked_at)
+					.map_or(Duration::zero(), |until| (until - config.now()).max(Duration::zero()))
+			}
+
+			pub $($async)? fn extend(&self) -> LockResult<bool> {
+				self.lock.extend()$($await)*
+			}
+
+			/// Release the lock now, the guard does not release it again when dropped.
+			pub $($async)? fn release(self) -> LockResult<()> {
+				let lock = self.lock;
+				std::mem::forget(self);
+				lock.release()$($await)*
+			}
+		}
+
+		impl<T: $lockable> Drop for $guard<'_, T> {
+			fn drop(&mut self) {
+				self.lock.release_on_drop();
+			}
+		}
 	};
 }
 
-#[cfg(not(any(feature = "tokio", feature = "async-std")))]
-impl_dist_lock!(,);
+impl_dist_lock!(
+	/// A distributed lock backed by a blocking `Lockable` driver.
+	DistLock,
+	LockGuard,
+	Lockable,
+	retry,
+	LockOwner::current_thread,
+	,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_dist_lock!(
+	/// A distributed lock backed by an `AsyncLockable` driver.
+	///
+	/// Tasks share worker threads, so a reentrant lock without an explicit owner gets an owner of
+	/// its own instead of the thread owner. Pass the same `LockOwner` to `LockConfig::with_owner`
+	/// for locks that should share the holds.
+	AsyncDistLock,
+	AsyncLockGuard,
+	AsyncLockable,
+	retry_async,
+	LockOwner::new,
+	async,
+	.await
+);
+
+impl<T: Lockable> DistLock<T> {
+	fn release_on_drop(&self) {
+		if self.state.get().is_locked {
+			let _ = telemetry::on_drop(&self.config, self.driver.provider(), || self.release());
+		}
+	}
+}
 
 #[cfg(any(feature = "tokio", feature = "async-std"))]
-impl_dist_lock!(async, .await);
+impl<T: AsyncLockable> AsyncDistLock<T> {
+	fn release_on_drop(&self) {
+		if self.state.get().is_locked {
+			let provider = self.driver.provider();
+			let _ = telemetry::on_drop(&self.config, provider, || block_on(self.release()));
+		}
+	}
+}
+
+/// Run a release from `Drop` to completion on the current runtime.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+	cfg_if::cfg_if! {
+		if #[cfg(feature = "tokio")] {
+			tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+		} else {
+			async_std::task::block_on(future)
+		}
+	}
+}
+
+/// Marks the lock as possibly held when an acquire is cancelled mid request, so that a later
+/// owner-checked release cleans it up.
+pub(crate) struct PendingAcquire<'a> {
+	pub(crate) state: &'a StateCell,
+}
+
+impl Drop for PendingAcquire<'_> {
+	fn drop(&mut self) {
+		self.state.set(LockState::new(true, DateTime::<Utc>::MIN_UTC));
+	}
+}
 
 #[derive(Debug, Clone)]
 pub struct LockConfig {
 	pub(super) name: String,
 	pub(super) min_lock: Duration,
 	pub(super) max_lock: Duration,
+	pub(super) owner: Option<LockOwner>,
+	pub(super) reentrant: bool,
+	pub(super) queue_timeout: Option<Duration>,
+	pub(super) drift: Duration,
+	pub(super) backend_time: bool,
+	pub(super) namespace: Option<String>,
+	pub(super) retry: RetryPolicy,
+	pub(super) clock: Arc<dyn Clock>,
 }
 
 impl LockConfig {
+	/// A config taken as is, see `builder` for a validated one.
 	pub fn new(name: String, min_lock: Duration, max_lock: Duration) -> LockConfig {
-		LockConfig { name, min_lock, max_lock }
+		LockConfig {
+			name,
+			min_lock,
+			max_lock,
+			owner: None,
+			reentrant: false,
+			queue_timeout: None,
+			drift: Duration::zero(),
+			backend_time: false,
+			namespace: None,
+			retry: RetryPolicy::default(),
+			clock: Arc::new(SystemClock),
+		}
+	}
+
+	/// Build a config for `name` that is checked by `LockConfigBuilder::build`, `max_lock` has to
+	/// be set.
+	pub fn builder<S: Into<String>>(name: S) -> LockConfigBuilder {
+		LockConfigBuilder { config: Self::new(name.into(), Duration::zero(), Duration::zero()) }
 	}
 
 	pub fn from_mills(name: String, min_lock: i64, max_lock: i64) -> LockConfig {
@@ -129,8 +413,113 @@ impl LockConfig {
 		&self.max_lock
 	}
 
+	/// Use a fixed owner instead of the one generated by `DistLock::new`.
+	pub fn with_owner(mut self, owner: LockOwner) -> LockConfig {
+		self.owner = Some(owner);
+		self
+	}
+
+	/// Count holds in the backend so that the same owner can acquire the lock again, each
+	/// `acquire` then needs a matching `release`.
+	///
+	/// Without an explicit owner `DistLock::new` uses `LockOwner::current_thread`, so locks created
+	/// on the same thread share the holds, while `AsyncDistLock::new` generates a new owner for
+	/// every lock. Every user of a lock name must agree on the mode.
+	pub fn with_reentrant(mut self, reentrant: bool) -> LockConfig {
+		self.reentrant = reentrant;
+		self
+	}
+
+	pub fn is_reentrant(&self) -> bool {
+		self.reentrant
+	}
+
+	/// Grant the lock in arrival order. A failed `acquire` queues the owner, who keeps its place
+	/// by calling `acquire` again within `queue_timeout`, `acquire_with` does that until it gets
+	/// the lock or gives up. Applies to plain locks, a reentrant lock ignores the queue.
+	pub fn with_fair(mut self, queue_timeout: Duration) -> LockConfig {
+		self.queue_timeout = Some(queue_timeout);
+		self
+	}
+
+	pub fn is_fair(&self) -> bool {
+		self.queue_timeout.is_some()
+	}
+
+	pub fn queue_timeout(&self) -> Option<&Duration> {
+		self.queue_timeout.as_ref()
+	}
+
+	/// Allow `drift` of clock skew between hosts: the local view of a lock expires that much
+	/// earlier, and SQL and ZooKeeper only take over an expired lock once it has been expired for
+	/// that long. Redis expires locks on its own clock and needs no allowance. Unlike the builder
+	/// this doesn't check the drift, one of `max_lock` or more makes every lock expire locally.
+	pub fn with_drift(mut self, drift: Duration) -> LockConfig {
+		self.drift = drift;
+		self
+	}
+
+	pub fn drift(&self) -> &Duration {
+		&self.drift
+	}
+
+	/// Let the backend clock decide expiry of `DistLock` locks: Redis `TIME`, the database time of
+	/// SQL or the `mtime` of a ZooKeeper node written for the purpose. Costs one more request per
+	/// operation, read-write locks, semaphores and multi-locks keep using the local clock.
+	pub fn with_backend_time(mut self, backend_time: bool) -> LockConfig {
+		self.backend_time = backend_time;
+		self
+	}
+
+	pub fn uses_backend_time(&self) -> bool {
+		self.backend_time
+	}
+
+	/// Namespace the name was prefixed with by the builder.
+	pub fn namespace(&self) -> Option<&str> {
+		self.namespace.as_deref()
+	}
+
+	/// Policy of `DistLock::lock` and `DistLock::acquire_timeout`, retries every 100ms by default.
+	pub fn retry_policy(&self) -> &RetryPolicy {
+		&self.retry
+	}
+
+	/// Read the local time from `clock` instead of the system clock, see `clock`.
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> LockConfig {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	pub fn clock(&self) -> &Arc<dyn Clock> {
+		&self.clock
+	}
+
+	/// Current time of the clock of this config.
+	pub fn now(&self) -> DateTime<Utc> {
+		self.clock.now()
+	}
+
+	/// End of the local view of a lock taken at `locked_at`, `None` if the drift moves it before
+	/// the earliest representable time.
+	pub(crate) fn local_until(&self, locked_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+		locked_at.checked_add_signed(self.max_lock - self.drift)
+	}
+
+	/// Owner stored by the providers, falls back to a per-process owner if the config is used
+	/// without a `DistLock`.
+	pub fn owner(&self) -> &LockOwner {
+		static PROCESS_OWNER: OnceLock<LockOwner> = OnceLock::new();
+		self.owner.as_ref().unwrap_or_else(|| PROCESS_OWNER.get_or_init(LockOwner::new))
+	}
+
+	/// Time the lock must still be held for after a release, zero once `min_lock` has passed.
+	pub fn at_least_remaining(&self, locked_at: DateTime<Utc>) -> Duration {
+		(locked_at + self.min_lock - self.now()).max(Duration::zero())
+	}
+
 	pub fn lock_at_least_until(&self, locked_at: DateTime<Utc>) -> DateTime<Utc> {
-		let now = Utc::now();
+		let now = self.now();
 		let min_lock_until = locked_at + self.min_lock;
 		if min_lock_until > now {
 			min_lock_until
@@ -140,38 +529,288 @@ impl LockConfig {
 	}
 }
 
+/// Builder of a `LockConfig` whose durations and names are checked, see `LockConfig::builder`.
+#[derive(Debug, Clone)]
+pub struct LockConfigBuilder {
+	config: LockConfig,
+}
+
+impl LockConfigBuilder {
+	pub fn min_lock(mut self, min_lock: Duration) -> Self {
+		self.config.min_lock = min_lock;
+		self
+	}
+
+	pub fn max_lock(mut self, max_lock: Duration) -> Self {
+		self.config.max_lock = max_lock;
+		self
+	}
+
+	/// Store the lock under this owner id instead of a generated one, see `LockConfig::with_owner`.
+	pub fn owner<S: Into<String>>(mut self, id: S) -> Self {
+		self.config.owner = Some(LockOwner::from_id(id));
+		self
+	}
+
+	/// See `LockConfig::with_drift`.
+	pub fn drift(mut self, drift: Duration) -> Self {
+		self.config.drift = drift;
+		self
+	}
+
+	/// Prefix the lock name with `{namespace}:`, drivers of the lock should be created with the
+	/// resulting `LockConfig::name`.
+	pub fn namespace<S: Into<String>>(mut self, namespace: S) -> Self {
+		self.config.namespace = Some(namespace.into());
+		self
+	}
+
+	/// See `LockConfig::with_backend_time`.
+	pub fn backend_time(mut self, backend_time: bool) -> Self {
+		self.config.backend_time = backend_time;
+		self
+	}
+
+	pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+		self.config.retry = policy;
+		self
+	}
+
+	/// See `LockConfig::with_clock`.
+	pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.config.clock = Arc::new(clock);
+		self
+	}
+
+	/// See `LockConfig::with_reentrant`.
+	pub fn reentrant(mut self, reentrant: bool) -> Self {
+		self.config.reentrant = reentrant;
+		self
+	}
+
+	/// See `LockConfig::with_fair`.
+	pub fn fair(mut self, queue_timeout: Duration) -> Self {
+		self.config.queue_timeout = Some(queue_timeout);
+		self
+	}
+
+	pub fn build(self) -> Result<LockConfig, ConfigError> {
+		let mut config = self.config;
+		if config.name.is_empty() {
+			return Err(ConfigError::EmptyName);
+		}
+
+		if config.owner.as_ref().is_some_and(|owner| owner.id().is_empty()) {
+			return Err(ConfigError::EmptyOwner);
+		}
+
+		if config.max_lock <= Duration::zero() {
+			return Err(ConfigError::NonPositiveMaxLock(config.max_lock));
+		}
+
+		if config.min_lock < Duration::zero() {
+			return Err(ConfigError::NegativeMinLock(config.min_lock));
+		}
+
+		if config.min_lock > config.max_lock {
+			return Err(ConfigError::MinLockExceedsMaxLock {
+				min_lock: config.min_lock,
+				max_lock: config.max_lock,
+			});
+		}
+
+		if config.drift < Duration::zero() || config.drift >= config.max_lock {
+			return Err(ConfigError::InvalidDrift(config.drift));
+		}
+
+		if let Some(queue_timeout) =
+			config.queue_timeout.filter(|timeout| timeout <= &Duration::zero())
+		{
+			return Err(ConfigError::NonPositiveQueueTimeout(queue_timeout));
+		}
+
+		if let Some(namespace) = &config.namespace {
+			if namespace.is_empty() {
+				return Err(ConfigError::EmptyNamespace);
+			}
+			config.name = format!("{}:{}", namespace, config.name);
+		}
+
+		Ok(config)
+	}
+}
+
+pub(crate) fn random_u64() -> u64 {
+	static SEQ: AtomicU64 = AtomicU64::new(0);
+
+	let mut hasher = RandomState::new().build_hasher();
+	hasher.write_u64(SEQ.fetch_add(1, Ordering::Relaxed));
+	hasher.finish()
+}
+
+/// Identity of a lock holder, formatted as `hostname:pid:instance` by default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LockOwner(String);
+
+impl LockOwner {
+	pub fn new() -> LockOwner {
+		LockOwner(format!(
+			"{}:{}:{:016x}",
+			gethostname().to_string_lossy(),
+			process::id(),
+			random_u64()
+		))
+	}
+
+	/// Owner shared by everything running on the current thread.
+	pub fn current_thread() -> LockOwner {
+		thread_local! {
+			static THREAD_OWNER: LockOwner = LockOwner::new();
+		}
+
+		THREAD_OWNER.with(LockOwner::clone)
+	}
+
+	pub fn from_id<S: Into<String>>(id: S) -> LockOwner {
+		LockOwner(id.into())
+	}
+
+	pub fn id(&self) -> &str {
+		&self.0
+	}
+}
+
+impl Default for LockOwner {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Display for LockOwner {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+/// Local view of a lock.
+///
+/// `token` is a fencing token that increases with every successful acquisition of a lock name,
+/// downstream stores can reject writes carrying a token lower than the latest one they have seen.
+/// Redis keeps an `INCR` counter, SQL a `version` column and ZooKeeper uses the zxid of the last
+/// write to the lock node, so the ZooKeeper token also advances on extend.
+///
+/// `holds` is the hold count stored in the backend for a reentrant lock, 1 for any other held lock.
 #[derive(Debug, Default, Clone, Copy)]
 pub struct LockState {
 	pub(super) is_locked: bool,
 	pub(super) locked_at: DateTime<Utc>,
+	pub(super) token: i64,
+	pub(super) holds: u32,
 }
 
 impl LockState {
 	pub const fn unlock() -> LockState {
-		Self { is_locked: false, locked_at: DateTime::<Utc>::MIN_UTC }
+		Self { is_locked: false, locked_at: DateTime::<Utc>::MIN_UTC, token: 0, holds: 0 }
 	}
 	pub const fn new(is_locked: bool, locked_at: DateTime<Utc>) -> LockState {
-		Self { is_locked, locked_at }
+		Self { is_locked, locked_at, token: 0, holds: is_locked as u32 }
+	}
+
+	pub const fn with_token(mut self, token: i64) -> LockState {
+		self.token = token;
+		self
+	}
+
+	pub const fn with_holds(mut self, holds: u32) -> LockState {
+		self.holds = holds;
+		self
 	}
 
 	pub fn check_locked(&self, config: &LockConfig) -> bool {
-		if self.is_locked {
-			let until = self.locked_at + config.max_lock;
-			until > Utc::now()
-		} else {
-			false
-		}
+		self.is_locked
+			&& config.local_until(self.locked_at).is_some_and(|until| until > config.now())
+	}
+
+	pub fn is_locked(&self) -> bool {
+		self.is_locked
 	}
 
 	pub fn lock_time(&self) -> DateTime<Utc> {
 		self.locked_at
 	}
+
+	pub fn fencing_token(&self) -> i64 {
+		self.token
+	}
+
+	pub fn holds(&self) -> u32 {
+		self.holds
+	}
+}
+
+/// A live lock as seen in the backend, returned by `inspect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockInfo {
+	pub(super) name: String,
+	pub(super) holder: LockOwner,
+	pub(super) locked_at: DateTime<Utc>,
+	pub(super) lock_until: DateTime<Utc>,
+	pub(super) token: Option<i64>,
+	pub(super) holds: u32,
+}
+
+impl LockInfo {
+	pub fn new(
+		name: impl Into<String>,
+		holder: LockOwner,
+		locked_at: DateTime<Utc>,
+		lock_until: DateTime<Utc>,
+	) -> LockInfo {
+		Self { name: name.into(), holder, locked_at, lock_until, token: None, holds: 1 }
+	}
+
+	pub fn with_token(mut self, token: i64) -> LockInfo {
+		self.token = Some(token);
+		self
+	}
+
+	pub fn with_holds(mut self, holds: u32) -> LockInfo {
+		self.holds = holds;
+		self
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn holder(&self) -> &LockOwner {
+		&self.holder
+	}
+
+	/// When the lock was taken, or last extended for drivers that rewrite it on extension.
+	pub fn locked_at(&self) -> DateTime<Utc> {
+		self.locked_at
+	}
+
+	pub fn lock_until(&self) -> DateTime<Utc> {
+		self.lock_until
+	}
+
+	/// Fencing token of the holder, `None` if the driver doesn't keep one.
+	pub fn fencing_token(&self) -> Option<i64> {
+		self.token
+	}
+
+	/// Reentrant holds of the holder, 0 while the lock is only kept for its `min_lock`.
+	pub fn holds(&self) -> u32 {
+		self.holds
+	}
 }
 
 macro_rules! impl_lockable {
-	($($async: ident)?) => {
-		#[cfg_attr(any(feature = "tokio", feature = "async-std"), async_trait::async_trait)]
-		pub trait Lockable {
+	($(#[$meta: meta])* $lockable: ident $(: $bound: ident)?, $($async: ident)?) => {
+		$(#[$meta])*
+		pub trait $lockable $(: $bound)? {
 			$($async)? fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState>;
 
 			$($async)? fn release_lock(
@@ -181,29 +820,40 @@ macro_rules! impl_lockable {
 			) -> LockResult<LockState>;
 
 			$($async)? fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState>;
-		}
-	};
-}
-
-#[cfg(not(any(feature = "tokio", feature = "async-std")))]
-impl_lockable!();
 
-#[cfg(any(feature = "tokio", feature = "async-std"))]
-impl_lockable!(async);
+			/// Remove the owner from the queue of a fair lock, nothing to do for drivers without
+			/// fair locks.
+			$($async)? fn leave_queue(&self, _config: &LockConfig) -> LockResult<()> {
+				Ok(())
+			}
 
-impl<T: Lockable> Drop for DistLock<T> {
-	fn drop(&mut self) {
-		if self.state.get().is_locked {
-			#[cfg(feature = "tokio")]
-			tokio::task::block_in_place(|| {
-				let _ = tokio::runtime::Handle::current().block_on(self.release());
-			});
+			/// The live lock on `config.name` as stored in the backend, `None` if the lock is free.
+			/// Fails with `LockError::InvalidLock` for drivers that can't read it back.
+			$($async)? fn inspect(&self, _config: &LockConfig) -> LockResult<Option<LockInfo>> {
+				Err(LockError::InvalidLock("inspect is unsupported".to_string()))
+			}
 
-			#[cfg(feature = "async-std")]
-			let _ = async_std::task::block_on(self.release());
+			/// Hook the driver's own failure detection, such as a session expiry, into `signal`.
+			fn watch_lost(&self, _signal: &LostSignal) {}
 
-			#[cfg(not(any(feature = "tokio", feature = "async-std")))]
-			let _ = self.release();
+			/// Name of the backend, used to label metrics.
+			fn provider(&self) -> &'static str {
+				"custom"
+			}
 		}
-	}
+	};
 }
+
+impl_lockable!(
+	/// A blocking lock provider.
+	Lockable,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_lockable!(
+	/// An async lock provider, available with the `tokio` or `async-std` feature. Drivers are
+	/// `Sync` as their futures borrow them across await points.
+	#[async_trait::async_trait]
+	AsyncLockable: Sync,
+	async
+);
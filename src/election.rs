@@ -0,0 +1,230 @@
+//! Leader election on top of `DistLock`: the candidate holding the lock is the leader.
+//!
+//! A `LeaderElector` campaigns by acquiring the lock and keeps it with a `Watchdog` until it
+//! resigns or the lease is lost. The term of a leadership is the fencing token it was elected
+//! with, so a newer leader always has a higher term. Anyone can ask the driver who the current
+//! leader is, that is the owner stored with the lock.
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::AsyncDistLock;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::AsyncLockable;
+use crate::core::DistLock;
+use crate::core::LockConfig;
+use crate::core::LockOwner;
+use crate::core::Lockable;
+use crate::error::LockResult;
+use crate::retry::RetryPolicy;
+use crate::signal::LostReason;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::watchdog::AsyncWatchdog;
+use crate::watchdog::Watchdog;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderEvent {
+	/// Leadership was gained with the given term.
+	Elected(i64),
+	/// Leadership was given up with `resign`.
+	Resigned,
+	/// Leadership was lost without resigning, see `LostSignal`.
+	Lost(LostReason),
+}
+
+type Callback = Arc<dyn Fn(LeaderEvent) + Send + Sync>;
+
+#[derive(Default)]
+struct Listeners {
+	senders: Vec<Sender<LeaderEvent>>,
+	callbacks: Vec<Callback>,
+}
+
+/// Callbacks and channels notified of leadership changes.
+#[derive(Clone, Default)]
+struct Events {
+	listeners: Arc<Mutex<Listeners>>,
+}
+
+impl Events {
+	fn emit(&self, event: LeaderEvent) {
+		let callbacks = {
+			let mut listeners = self.lock();
+			listeners.senders.retain(|sender| sender.send(event).is_ok());
+			listeners.callbacks.clone()
+		};
+
+		callbacks.iter().for_each(|callback| callback(event));
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Listeners> {
+		self.listeners.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+macro_rules! impl_leader_elector {
+	(
+		$(#[$meta: meta])*
+		$elector: ident,
+		$lock: ident,
+		$lockable: ident,
+		$watchdog: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		pub struct $elector<T: $lockable> {
+			lock: Arc<$lock<T>>,
+			term: AtomicI64,
+			watchdog: Mutex<Option<$watchdog>>,
+			events: Events,
+		}
+
+		impl<T: $lockable + Send + Sync + 'static> $elector<T> {
+			/// A candidate for the leadership of `config.name`, identified by the owner of
+			/// `config`.
+			pub fn new(config: LockConfig, driver: T) -> Self {
+				let lock = Arc::new($lock::new(config, driver));
+				let events = Events::default();
+				let lost = events.clone();
+				lock.lost_signal().on_lost(move |reason| lost.emit(LeaderEvent::Lost(reason)));
+				$elector { lock, term: AtomicI64::new(0), watchdog: Mutex::new(None), events }
+			}
+
+			pub fn lock(&self) -> &Arc<$lock<T>> {
+				&self.lock
+			}
+
+			/// Owner this candidate is stored as when it leads.
+			pub fn id(&self) -> &LockOwner {
+				self.lock.config().owner()
+			}
+
+			pub fn is_leader(&self) -> bool {
+				self.lock.state().check_locked(self.lock.config())
+			}
+
+			/// Term of the current leadership of this candidate, `None` if it doesn't lead.
+			pub fn term(&self) -> Option<i64> {
+				self.is_leader().then(|| self.term.load(Ordering::Acquire))
+			}
+
+			/// Owner of the current leader as stored in the backend, `None` if there is none.
+			pub $($async)? fn leader(&self) -> LockResult<Option<LockOwner>> {
+				let info = self.lock.driver().inspect(self.lock.config())$($await)*?;
+				Ok(info.map(|info| info.holder))
+			}
+
+			/// Become the leader if there is none, `true` if this candidate leads.
+			pub $($async)? fn try_campaign(&self) -> LockResult<bool> {
+				if self.is_leader() {
+					return Ok(true);
+				}
+
+				if self.lock.acquire()$($await)*? {
+					self.elected();
+				}
+				Ok(self.is_leader())
+			}
+
+			/// Wait until this candidate is the leader, retrying following the retry policy of
+			/// the config.
+			pub $($async)? fn campaign(&self) -> LockResult<()> {
+				self.campaign_with(*self.lock.config().retry_policy())$($await)*
+			}
+
+			/// Retry `try_campaign` following `policy`, see `DistLock::acquire_with`.
+			pub $($async)? fn campaign_with(&self, policy: RetryPolicy) -> LockResult<()> {
+				if self.is_leader() {
+					return Ok(());
+				}
+
+				self.lock.acquire_with(policy)$($await)*?;
+				self.elected();
+				Ok(())
+			}
+
+			/// Give up the leadership, does nothing if this candidate doesn't lead.
+			pub $($async)? fn resign(&self) -> LockResult<()> {
+				let watchdog = self.watchdog().take();
+				if let Some(watchdog) = watchdog {
+					// a renewal failure already fired the lost signal
+					let _ = watchdog.stop()$($await)*;
+				}
+
+				if !self.lock.state().is_locked() {
+					return Ok(());
+				}
+
+				self.lock.release()$($await)*?;
+				self.events.emit(LeaderEvent::Resigned);
+				Ok(())
+			}
+
+			/// Call `callback` on every leadership change of this candidate.
+			pub fn on_change<F>(&self, callback: F)
+			where
+				F: Fn(LeaderEvent) + Send + Sync + 'static,
+			{
+				self.events.lock().callbacks.push(Arc::new(callback));
+			}
+
+			/// Receive every leadership change of this candidate.
+			pub fn subscribe(&self) -> Receiver<LeaderEvent> {
+				let (sender, receiver) = mpsc::channel();
+				self.events.lock().senders.push(sender);
+				receiver
+			}
+
+			fn elected(&self) {
+				let term = self.lock.state().fencing_token();
+				self.term.store(term, Ordering::Release);
+				*self.watchdog() = Some($watchdog::spawn(&self.lock));
+				self.events.emit(LeaderEvent::Elected(term));
+			}
+
+			fn watchdog(&self) -> MutexGuard<'_, Option<$watchdog>> {
+				self.watchdog.lock().unwrap_or_else(PoisonError::into_inner)
+			}
+		}
+
+		impl<T: $lockable> std::fmt::Debug for $elector<T> {
+			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+				f.debug_struct(stringify!($elector))
+					.field("config", self.lock.config())
+					.field("state", &self.lock.state())
+					.field("term", &self.term)
+					.finish()
+			}
+		}
+	};
+}
+
+impl_leader_elector!(
+	/// A leader election candidate backed by a blocking `Lockable` driver, its lease is renewed
+	/// from a `Watchdog` thread while it leads.
+	LeaderElector,
+	DistLock,
+	Lockable,
+	Watchdog,
+	,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_leader_elector!(
+	/// A leader election candidate backed by an `AsyncLockable` driver, its lease is renewed from
+	/// an `AsyncWatchdog` task while it leads.
+	AsyncLeaderElector,
+	AsyncDistLock,
+	AsyncLockable,
+	AsyncWatchdog,
+	async,
+	.await
+);
@@ -1,3 +1,5 @@
+use chrono::Duration;
+
 pub type LockResult<T> = core::result::Result<T, crate::error::LockError>;
 
 #[derive(Debug, thiserror::Error)]
@@ -22,12 +24,60 @@ pub enum LockError {
 	#[error("Zookeeper error: {0}")]
 	ZkError(#[from] ::zookeeper::ZkError),
 
+	#[cfg(feature = "etcd")]
+	#[error("Etcd error: {0}")]
+	EtcdError(#[from] etcd_client::Error),
+
+	#[cfg(feature = "file")]
+	#[error("File error: {0}")]
+	IoError(#[from] std::io::Error),
+
 	#[error("lock failed")]
 	LockFailed,
 
 	#[error("lock released")]
 	LockReleased,
 
+	#[error("lock timeout")]
+	Timeout,
+
+	#[error("lock not owned: {0}")]
+	NotOwner(String),
+
 	#[error("invalid error: {0}")]
 	InvalidLock(String),
+
+	#[error("invalid config: {0}")]
+	InvalidConfig(#[from] ConfigError),
+}
+
+/// A `LockConfig` invariant violated, returned by `LockConfigBuilder::build`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigError {
+	#[error("lock name is empty")]
+	EmptyName,
+
+	#[error("namespace is empty")]
+	EmptyNamespace,
+
+	#[error("owner id is empty")]
+	EmptyOwner,
+
+	#[error("max_lock must be positive: {0}")]
+	NonPositiveMaxLock(Duration),
+
+	#[error("min_lock must not be negative: {0}")]
+	NegativeMinLock(Duration),
+
+	#[error("min_lock {min_lock} exceeds max_lock {max_lock}")]
+	MinLockExceedsMaxLock { min_lock: Duration, max_lock: Duration },
+
+	#[error("drift must be at least zero and below max_lock: {0}")]
+	InvalidDrift(Duration),
+
+	#[error("queue timeout must be positive: {0}")]
+	NonPositiveQueueTimeout(Duration),
+
+	#[error("lease must be at least one second: {0}")]
+	SubSecondLease(Duration),
 }
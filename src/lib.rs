@@ -14,6 +14,21 @@
 //! - `diesel_postgres_r2d2`: Enable diesel/postgres and diesel/r2d2.
 //! - `diesel_mysql_r2d2`: Enable diesel/postgres and diesel/r2d2.
 //! - `zookeeper`: Use zookeeper as state store backend.
+//! - `memory`: Keep locks in process memory with `MemoryDriver`, for tests and single-process use.
+//! - `file`: Keep locks in lock files of a directory with `FileDriver`, for one host or a shared
+//!   volume.
+//! - `etcd`: Async lock on etcd leases and transactions with `EtcdDriver`.
+//! - `metrics`: Record lock metrics through the `metrics` facade, see `telemetry`.
+//! - `tracing`: Trace lock calls and driver internals with `tracing` spans and events.
+//!
+//! Blocking drivers implement `Lockable` and are used through `DistLock`, the async features add
+//! `AsyncLockable` impls used through `AsyncDistLock`. Both can be used in the same build.
+//! `DistRwLock` in `rwlock` adds shared read and exclusive write holds on top of the same drivers,
+//! `DistSemaphore` in `semaphore` hands out up to N permits and `MultiLock` in `multilock` holds
+//! several names at once. `LeaderElector` in `election` elects a leader among the holders of a
+//! lock name, and `LockAdmin` in `admin` lists every lock of a backend and force-releases them.
+//! Locks read the time from the `Clock` of their config, a `MockClock` from `clock` lets tests
+//! move it forward instead of sleeping.
 //!
 //! # Examples
 //!
@@ -44,6 +59,11 @@
 //! dist_lock.acquire();
 //! dist_lock.extend();
 //! dist_lock.release();
+//!
+//! // or hold a guard that releases the lock on drop.
+//! if let Some(guard) = dist_lock.try_lock()? {
+//!     guard.extend()?;
+//! }
 //! ```
 //!
 //! OR
@@ -70,9 +90,19 @@
 //!     })?)
 //! }
 //! ```
+pub mod admin;
+pub mod clock;
 pub mod core;
+pub mod election;
 pub mod error;
+pub mod multilock;
 pub mod provider;
+pub mod retry;
+pub mod rwlock;
+pub mod semaphore;
+pub mod signal;
+pub mod telemetry;
+pub mod watchdog;
 
 pub use dist_lock_codegen::dist_lock;
 
@@ -0,0 +1,350 @@
+//! Hold locks on several names at once, all or nothing.
+//!
+//! Names are sorted and deduplicated so that every `MultiLock` takes them in the same order.
+//! Redis acquires them in one script and Diesel in one transaction. Other drivers, such as
+//! ZooKeeper whose client has no `multi()` or a Redis cluster where the names fall in different
+//! hash slots, acquire them one by one with `acquire_each` and release what was taken when a name
+//! is held by someone else. The locks are plain `DistLock` locks, so a
+//! `DistLock` on one of the names excludes the `MultiLock` and the other way around.
+use chrono::DateTime;
+use chrono::Utc;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::block_on;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::AsyncLockable;
+use crate::core::LockConfig;
+use crate::core::LockOwner;
+use crate::core::LockState;
+use crate::core::Lockable;
+use crate::core::PendingAcquire;
+use crate::core::StateCell;
+use crate::core::StateOpr;
+use crate::error::LockResult;
+use crate::retry::retry;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::retry::retry_async;
+use crate::retry::RetryPolicy;
+
+macro_rules! impl_multi_lockable {
+	($(#[$meta: meta])* $lockable: ident, $($async: ident)?) => {
+		$(#[$meta])*
+		pub trait $lockable {
+			/// Lock every config, the returned states are either all locked or all unlocked.
+			$($async)? fn acquire_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>>;
+
+			$($async)? fn release_locks(
+				&self,
+				configs: &[LockConfig],
+				states: &[LockState],
+			) -> LockResult<Vec<LockState>>;
+
+			$($async)? fn extend_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>>;
+		}
+	};
+}
+
+impl_multi_lockable!(
+	/// A blocking provider of all-or-nothing locks on several names.
+	MultiLockable,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_multi_lockable!(
+	/// An async provider of all-or-nothing locks on several names, available with the `tokio` or
+	/// `async-std` feature.
+	#[async_trait::async_trait]
+	AsyncMultiLockable,
+	async
+);
+
+macro_rules! impl_each {
+	(
+		$lockable: ident,
+		$acquire: ident,
+		$release: ident,
+		$extend: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		/// `acquire_locks` for drivers without multi-name requests: lock the configs in order and
+		/// release the ones taken as soon as one fails, without their at least hold.
+		pub $($async)? fn $acquire<T: $lockable>(
+			driver: &T,
+			configs: &[LockConfig],
+		) -> LockResult<Vec<LockState>> {
+			let mut states = Vec::with_capacity(configs.len());
+			for config in configs {
+				let state = match driver.acquire_lock(config)$($await)* {
+					Ok(state) if state.is_locked => state,
+					result => {
+						for config in &configs[..states.len()] {
+							let _ = driver.release_lock(config, &LockState::unlock())$($await)*;
+						}
+						result?;
+						return Ok(vec![LockState::unlock(); configs.len()]);
+					}
+				};
+				states.push(state);
+			}
+
+			Ok(states)
+		}
+
+		/// `release_locks` for drivers without multi-name requests, releases every config and
+		/// returns the first error.
+		pub $($async)? fn $release<T: $lockable>(
+			driver: &T,
+			configs: &[LockConfig],
+			states: &[LockState],
+		) -> LockResult<Vec<LockState>> {
+			let mut results = Vec::with_capacity(configs.len());
+			for (config, state) in configs.iter().zip(states) {
+				results.push(driver.release_lock(config, state)$($await)*);
+			}
+			results.into_iter().collect()
+		}
+
+		/// `extend_locks` for drivers without multi-name requests.
+		pub $($async)? fn $extend<T: $lockable>(
+			driver: &T,
+			configs: &[LockConfig],
+		) -> LockResult<Vec<LockState>> {
+			let mut states = Vec::with_capacity(configs.len());
+			for config in configs {
+				states.push(driver.extend_lock(config)$($await)*?);
+			}
+			Ok(states)
+		}
+	};
+}
+
+impl_each!(Lockable, acquire_each, release_each, extend_each,,);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_each!(
+	AsyncLockable,
+	acquire_each_async,
+	release_each_async,
+	extend_each_async,
+	async,
+	.await
+);
+
+macro_rules! impl_multi_lock {
+	(
+		$(#[$meta: meta])*
+		$lock: ident,
+		$guard: ident,
+		$lockable: ident,
+		$retry: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		#[derive(Debug)]
+		pub struct $lock<T: $lockable> {
+			configs: Vec<LockConfig>,
+			driver: T,
+			states: Vec<StateCell>,
+			create_at: DateTime<Utc>,
+		}
+
+		impl<T: $lockable> $lock<T> {
+			/// Lock `names` with the durations and owner of `config`, its name is ignored.
+			pub fn new<I, S>(mut config: LockConfig, names: I, driver: T) -> Self
+			where
+				I: IntoIterator<Item = S>,
+				S: Into<String>,
+			{
+				if config.owner.is_none() {
+					config.owner = Some(LockOwner::new());
+				}
+
+				let mut names = names.into_iter().map(Into::into).collect::<Vec<String>>();
+				names.sort();
+				names.dedup();
+
+				let configs = names
+					.into_iter()
+					.map(|name| LockConfig { name, ..config.clone() })
+					.collect::<Vec<_>>();
+				let states = configs.iter().map(|_| Default::default()).collect();
+				$lock { configs, driver, states, create_at: config.now() }
+			}
+
+			pub fn driver(&self) -> &T {
+				&self.driver
+			}
+
+			/// One config per name, in acquisition order.
+			pub fn configs(&self) -> &[LockConfig] {
+				&self.configs
+			}
+
+			pub fn states(&self) -> Vec<LockState> {
+				self.states.iter().map(StateOpr::get).collect()
+			}
+
+			pub fn is_locked(&self) -> bool {
+				self.states.iter().all(|state| state.get().is_locked)
+			}
+
+			pub fn create_at(&self) -> &DateTime<Utc> {
+				&self.create_at
+			}
+
+			/// Acquire every lock and return a guard that releases them on drop, `None` if any
+			/// of them is held by someone else.
+			pub $($async)? fn try_lock(&self) -> LockResult<Option<$guard<'_, T>>> {
+				if self.acquire()$($await)*? {
+					Ok(Some($guard { lock: self }))
+				} else {
+					Ok(None)
+				}
+			}
+
+			pub $($async)? fn acquire(&self) -> LockResult<bool> {
+				let locked = self
+					.configs
+					.iter()
+					.zip(&self.states)
+					.all(|(config, state)| state.get().check_locked(config));
+				if locked {
+					return Ok(true);
+				}
+
+				let pending =
+					self.states.iter().map(|state| PendingAcquire { state }).collect::<Vec<_>>();
+				let result = self.driver.acquire_locks(&self.configs)$($await)*;
+				pending.into_iter().for_each(std::mem::forget);
+
+				let states = match result {
+					Ok(states) => states,
+					Err(e) => {
+						self.set_states(&[]);
+						return Err(e);
+					}
+				};
+				self.set_states(&states);
+				Ok(self.is_locked())
+			}
+
+			/// Retry `acquire` following `policy`, see `DistLock::acquire_with`.
+			pub $($async)? fn acquire_with(&self, policy: RetryPolicy) -> LockResult<()> {
+				$retry(policy, move || self.acquire())$($await)*
+			}
+
+			pub $($async)? fn release(&self) -> LockResult<()> {
+				if self.states.iter().all(|state| !state.get().is_locked) {
+					return Ok(());
+				}
+
+				let result = self.driver.release_locks(&self.configs, &self.states())$($await)*;
+				self.set_states(result.as_deref().unwrap_or_default());
+				result.map(|_| ())
+			}
+
+			/// Extend every lock, `false` once any of them is lost.
+			pub $($async)? fn extend(&self) -> LockResult<bool> {
+				if !self.is_locked() {
+					return Ok(false);
+				}
+
+				let states = self.driver.extend_locks(&self.configs)$($await)*?;
+				self.set_states(&states);
+				Ok(self.is_locked())
+			}
+
+			/// Missing states count as unlocked.
+			fn set_states(&self, states: &[LockState]) {
+				for (i, cell) in self.states.iter().enumerate() {
+					cell.set(states.get(i).copied().unwrap_or_else(LockState::unlock));
+				}
+			}
+		}
+
+		impl<T: $lockable> Drop for $lock<T> {
+			fn drop(&mut self) {
+				self.release_on_drop();
+			}
+		}
+
+		/// Proof of holding every lock of a multi-lock, they are released when the guard is
+		/// dropped.
+		#[derive(Debug)]
+		#[must_use = "if unused the locks will be released immediately"]
+		pub struct $guard<'a, T: $lockable> {
+			lock: &'a $lock<T>,
+		}
+
+		impl<T: $lockable> $guard<'_, T> {
+			pub fn lock(&self) -> &$lock<T> {
+				self.lock
+			}
+
+			pub fn states(&self) -> Vec<LockState> {
+				self.lock.states()
+			}
+
+			/// Fencing tokens in the order of `MultiLock::configs`.
+			pub fn fencing_tokens(&self) -> Vec<i64> {
+				self.lock.states().iter().map(LockState::fencing_token).collect()
+			}
+
+			pub $($async)? fn extend(&self) -> LockResult<bool> {
+				self.lock.extend()$($await)*
+			}
+
+			/// Release the locks now, the guard does not release them again when dropped.
+			pub $($async)? fn release(self) -> LockResult<()> {
+				let lock = self.lock;
+				std::mem::forget(self);
+				lock.release()$($await)*
+			}
+		}
+
+		impl<T: $lockable> Drop for $guard<'_, T> {
+			fn drop(&mut self) {
+				self.lock.release_on_drop();
+			}
+		}
+	};
+}
+
+impl_multi_lock!(
+	/// Locks on several names backed by a blocking `MultiLockable` driver.
+	MultiLock,
+	MultiLockGuard,
+	MultiLockable,
+	retry,
+	,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_multi_lock!(
+	/// Locks on several names backed by an `AsyncMultiLockable` driver.
+	AsyncMultiLock,
+	AsyncMultiLockGuard,
+	AsyncMultiLockable,
+	retry_async,
+	async,
+	.await
+);
+
+impl<T: MultiLockable> MultiLock<T> {
+	fn release_on_drop(&self) {
+		if self.states.iter().any(|state| state.get().is_locked) {
+			let _ = self.release();
+		}
+	}
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl<T: AsyncMultiLockable> AsyncMultiLock<T> {
+	fn release_on_drop(&self) {
+		if self.states.iter().any(|state| state.get().is_locked) {
+			let _ = block_on(self.release());
+		}
+	}
+}
@@ -1,29 +1,84 @@
-use std::cell::RefCell;
 use std::fmt::Display;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
 
+use chrono::DateTime;
+use chrono::Duration;
 use chrono::Utc;
 use diesel::sql_types::BigInt;
+use diesel::sql_types::Integer;
 use diesel::sql_types::VarChar;
+use diesel::Connection;
+use diesel::OptionalExtension;
+use diesel::QueryableByName;
 use diesel::RunQueryDsl;
-use gethostname::gethostname;
 
+use crate::admin::LockAdmin;
 use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockOwner;
 use crate::core::LockState;
 use crate::core::Lockable;
+use crate::error::LockError;
 use crate::error::LockResult;
+use crate::multilock::MultiLockable;
+use crate::rwlock::RwLockable;
+use crate::rwlock::RwMode;
+use crate::semaphore::SemaphoreLockable;
+use crate::telemetry::event;
 
+use super::help::from_millis;
+use super::help::sql_stmt::count_permit_sql;
+use super::help::sql_stmt::count_rw_conflict_sql;
+use super::help::sql_stmt::delete_expired_sql;
+use super::help::sql_stmt::delete_permit_sql;
+use super::help::sql_stmt::delete_rw_sql;
+use super::help::sql_stmt::delete_waiter_sql;
 use super::help::sql_stmt::extend_lock_sql;
+use super::help::sql_stmt::extend_permit_sql;
+use super::help::sql_stmt::extend_rw_sql;
+use super::help::sql_stmt::force_release_lock_sql;
 use super::help::sql_stmt::insert_lock_sql;
+use super::help::sql_stmt::insert_permit_sql;
+use super::help::sql_stmt::insert_rw_sql;
+use super::help::sql_stmt::insert_waiter_sql;
+use super::help::sql_stmt::reenter_lock_sql;
+use super::help::sql_stmt::refresh_waiter_sql;
+use super::help::sql_stmt::release_hold_sql;
 use super::help::sql_stmt::release_lock_sql;
+use super::help::sql_stmt::release_permit_sql;
+use super::help::sql_stmt::release_rw_sql;
+use super::help::sql_stmt::select_head_waiter_sql;
+use super::help::sql_stmt::select_hold_sql;
+use super::help::sql_stmt::select_lock_sql;
+use super::help::sql_stmt::select_locks_sql;
 use super::help::sql_stmt::update_lock_sql;
+#[cfg(feature = "diesel_mysql")]
+use super::help::sql_stmt::MYSQL_NOW_SQL;
+#[cfg(feature = "diesel_postgres")]
+use super::help::sql_stmt::POSTGRES_NOW_SQL;
+#[cfg(feature = "diesel_sqlite")]
+use super::help::sql_stmt::SQLITE_NOW_SQL;
 
 const LOCK_TABLE: &str = "dist_lock";
+const RW_LOCK_TABLE: &str = "dist_rw_lock";
+const SEMAPHORE_TABLE: &str = "dist_semaphore";
+const WAITER_TABLE: &str = "dist_lock_waiter";
 
+/// Stores locks in the `dist_lock` table, the holds of a `DistRwLock` in `dist_rw_lock`, one row
+/// per lock name, mode and owner, and the permits of a `DistSemaphore` in `dist_semaphore`, one
+/// row per lock name and owner. Read-write holds and permits carry no fencing token. Waiters of a
+/// fair lock queue in `dist_lock_waiter`, in the order of its auto increment id.
+///
+/// See the README for the DDL of all tables and the columns to add when upgrading.
 #[derive(Debug)]
 pub struct DieselDriver<T> {
 	name: String,
 	table: String,
-	transport: RefCell<T>,
+	rw_table: String,
+	semaphore_table: String,
+	waiter_table: String,
+	transport: Mutex<T>,
 }
 
 impl<T> DieselDriver<T> {
@@ -31,13 +86,18 @@ impl<T> DieselDriver<T> {
 	where
 		P: Display,
 	{
+		let table_name = |table: &str| match &table_prefix {
+			Some(prefix) => format!("{}_{}", prefix, table),
+			None => table.to_owned(),
+		};
+
 		DieselDriver {
 			name: lock_name.to_owned(),
-			table: match table_prefix {
-				Some(prefix) => format!("{}_{}", prefix, LOCK_TABLE),
-				None => LOCK_TABLE.to_owned(),
-			},
-			transport: RefCell::new(transport),
+			table: table_name(LOCK_TABLE),
+			rw_table: table_name(RW_LOCK_TABLE),
+			semaphore_table: table_name(SEMAPHORE_TABLE),
+			waiter_table: table_name(WAITER_TABLE),
+			transport: Mutex::new(transport),
 		}
 	}
 
@@ -49,27 +109,195 @@ impl<T> DieselDriver<T> {
 		&self.table
 	}
 
-	pub fn transport(&self) -> &RefCell<T> {
+	pub fn rw_table(&self) -> &String {
+		&self.rw_table
+	}
+
+	pub fn semaphore_table(&self) -> &String {
+		&self.semaphore_table
+	}
+
+	pub fn waiter_table(&self) -> &String {
+		&self.waiter_table
+	}
+
+	/// The transport, behind a `Mutex` rather than the former `RefCell` so that the driver is
+	/// `Sync` and can be renewed by a `Watchdog` thread. A pool is only locked to clone its handle,
+	/// its connections are checked out and used outside of the mutex.
+	pub fn transport(&self) -> &Mutex<T> {
 		&self.transport
 	}
+
+	fn lock_transport(&self) -> LockResult<MutexGuard<'_, T>> {
+		self.transport
+			.lock()
+			.map_err(|_| LockError::InvalidLock("diesel transport is poisoned".to_string()))
+	}
+}
+
+#[cfg(any(
+	feature = "diesel_sqlite_r2d2",
+	feature = "diesel_postgres_r2d2",
+	feature = "diesel_mysql_r2d2"
+))]
+impl<M: ::r2d2::ManageConnection> DieselDriver<::r2d2::Pool<M>> {
+	/// A handle on the pool, the mutex is released before a connection is checked out.
+	fn pool(&self) -> LockResult<::r2d2::Pool<M>> {
+		Ok(self.lock_transport()?.clone())
+	}
+}
+
+#[derive(QueryableByName)]
+struct LockHold {
+	#[diesel(sql_type = BigInt)]
+	version: i64,
+	#[diesel(sql_type = Integer)]
+	holds: i32,
+}
+
+#[derive(QueryableByName)]
+struct LockedBy {
+	#[diesel(sql_type = VarChar)]
+	locked_by: String,
+}
+
+#[derive(QueryableByName)]
+struct LockRow {
+	#[diesel(sql_type = VarChar)]
+	name: String,
+	#[diesel(sql_type = VarChar)]
+	locked_by: String,
+	#[diesel(sql_type = BigInt)]
+	locked_at: i64,
+	#[diesel(sql_type = BigInt)]
+	lock_until: i64,
+	#[diesel(sql_type = BigInt)]
+	version: i64,
+	#[diesel(sql_type = Integer)]
+	holds: i32,
+}
+
+impl LockRow {
+	fn into_info(self) -> LockResult<LockInfo> {
+		let info = LockInfo::new(
+			self.name,
+			LockOwner::from_id(self.locked_by),
+			from_millis(self.locked_at)?,
+			from_millis(self.lock_until)?,
+		);
+		Ok(info.with_token(self.version).with_holds(self.holds as u32))
+	}
+}
+
+#[derive(QueryableByName)]
+struct HoldCount {
+	#[diesel(sql_type = BigInt)]
+	holds: i64,
+}
+
+#[derive(QueryableByName)]
+struct DbNow {
+	#[diesel(sql_type = BigInt)]
+	now: i64,
 }
 
 macro_rules! impl_lockable_diesel {
 	(
 		$client: ty,
 		$self: ident,
-		$conn: expr
+		$conn: expr,
+		$now_sql: expr
 	) => {
+		impl DieselDriver<$client> {
+			/// Time of the database if `config` uses backend time, otherwise of this host.
+			fn now(&$self, config: &LockConfig) -> LockResult<DateTime<Utc>> {
+				if !config.backend_time {
+					return Ok(config.now());
+				}
+
+				let millis = diesel::sql_query($now_sql).get_result::<DbNow>($conn)?.now;
+				from_millis(millis)
+			}
+
+			/// Fencing token and hold count of the row held by the owner of `config`.
+			fn held_state(&$self, config: &LockConfig) -> LockResult<LockState> {
+				let hold = diesel::sql_query(select_hold_sql(&$self.table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.get_result::<LockHold>($conn)
+					.optional()?;
+				Ok(match hold {
+					Some(hold) => LockState::new(true, config.now())
+						.with_token(hold.version)
+						.with_holds(hold.holds.max(1) as u32),
+					None => LockState::unlock(),
+				})
+			}
+
+			/// Queue the owner of `config` as a waiter of a fair lock until `now + queue_timeout`,
+			/// `true` if it is first in line.
+			fn enqueue(
+				&$self,
+				config: &LockConfig,
+				now: DateTime<Utc>,
+				queue_timeout: Duration,
+			) -> LockResult<bool> {
+				diesel::sql_query(delete_expired_sql(&$self.waiter_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)?;
+
+				let wait_until = now + queue_timeout;
+				let refreshed = diesel::sql_query(refresh_waiter_sql(&$self.waiter_table))
+					.bind::<BigInt, _>(wait_until.timestamp_millis())
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.execute($conn)?;
+				if refreshed == 0 {
+					diesel::sql_query(insert_waiter_sql(&$self.waiter_table))
+						.bind::<VarChar, _>(&config.name)
+						.bind::<VarChar, _>(config.owner().id())
+						.bind::<BigInt, _>(wait_until.timestamp_millis())
+						.execute($conn)?;
+				}
+
+				let head = diesel::sql_query(select_head_waiter_sql(&$self.waiter_table))
+					.bind::<VarChar, _>(&config.name)
+					.get_result::<LockedBy>($conn)
+					.optional()?;
+				Ok(head.is_some_and(|head| head.locked_by == config.owner().id()))
+			}
+		}
+
 		impl Lockable for DieselDriver<$client> {
 			fn acquire_lock(&$self, config: &LockConfig) -> LockResult<LockState> {
-				let now = Utc::now();
+				let now = $self.now(config)?;
 				let until = now + config.max_lock;
 
+				if config.reentrant {
+					let count = diesel::sql_query(reenter_lock_sql(&$self.table))
+						.bind::<BigInt, _>(until.timestamp_millis())
+						.bind::<VarChar, _>(&config.name)
+						.bind::<VarChar, _>(config.owner().id())
+						.bind::<BigInt, _>(now.timestamp_millis())
+						.execute($conn)?;
+					if count > 0 {
+						return $self.held_state(config);
+					}
+				}
+
+				let queue_timeout = config.queue_timeout.filter(|_| !config.reentrant);
+				if let Some(queue_timeout) = queue_timeout {
+					if !$self.enqueue(config, now, queue_timeout)? {
+						return Ok(LockState::unlock());
+					}
+				}
+
 				let mut locked = match diesel::sql_query(insert_lock_sql(&$self.table))
 				.bind::<VarChar, _>(&config.name)
 				.bind::<BigInt, _>(until.timestamp_millis())
 				.bind::<BigInt, _>(now.timestamp_millis())
-				.bind::<VarChar, _>(gethostname().to_string_lossy())
+				.bind::<VarChar, _>(config.owner().id())
 				.execute($conn)
 				{
 					Ok(count)  => count > 0,
@@ -77,63 +305,375 @@ macro_rules! impl_lockable_diesel {
 				};
 
 				if !locked {
+					event!(table = %$self.table, "lock row exists, falling back to update");
+					let expired_by =
+						now.checked_sub_signed(config.drift).unwrap_or(DateTime::<Utc>::MIN_UTC);
 					locked = diesel::sql_query(update_lock_sql(&$self.table))
 						.bind::<BigInt, _>(until.timestamp_millis())
 						.bind::<BigInt, _>(now.timestamp_millis())
-						.bind::<VarChar, _>(gethostname().to_string_lossy())
-						.bind::<VarChar, _>(&$self.name)
-						.bind::<BigInt, _>(until.timestamp_millis())
+						.bind::<VarChar, _>(config.owner().id())
+						.bind::<VarChar, _>(&config.name)
+						.bind::<BigInt, _>(expired_by.timestamp_millis())
 						.execute($conn)? > 0;
 				}
 
-				Ok(LockState::new(locked, Utc::now()))
+				if !locked {
+					return Ok(LockState::unlock());
+				}
+
+				if queue_timeout.is_some() {
+					$self.leave_queue(config)?;
+				}
+				$self.held_state(config)
 			}
 
 			fn release_lock(&$self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
-				let lock_until = config.lock_at_least_until(state.locked_at);
+				if config.reentrant {
+					let count = diesel::sql_query(release_hold_sql(&$self.table))
+						.bind::<VarChar, _>(&config.name)
+						.bind::<VarChar, _>(config.owner().id())
+						.execute($conn)?;
+					if count > 0 {
+						return Ok(state.with_holds($self.held_state(config)?.holds));
+					}
+				}
+
+				let lock_until = $self.now(config)? + config.at_least_remaining(state.locked_at);
 				diesel::sql_query(release_lock_sql(&$self.table))
 					.bind::<BigInt, _>(lock_until.timestamp_millis())
-					.bind::<VarChar, _>(&$self.name)
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
 					.execute($conn)?;
 				Ok(LockState::unlock())
 			}
 
 			fn extend_lock(&$self, config: &LockConfig) -> LockResult<LockState> {
-				let now = Utc::now();
+				let now = $self.now(config)?;
 				let until = now + config.max_lock;
 				let count = diesel::sql_query(extend_lock_sql(&$self.table))
 				.bind::<BigInt, _>(until.timestamp_millis())
-				.bind::<VarChar, _>(&$self.name)
-				.bind::<VarChar, _>(gethostname().to_string_lossy())
+				.bind::<VarChar, _>(&config.name)
+				.bind::<VarChar, _>(config.owner().id())
 				.bind::<BigInt, _>(now.timestamp_millis())
 				.execute($conn)?;
-				Ok(LockState::new(count > 0, Utc::now()))
+
+				if count == 0 {
+					return Ok(LockState::unlock());
+				}
+
+				$self.held_state(config)
+			}
+
+			fn leave_queue(&$self, config: &LockConfig) -> LockResult<()> {
+				diesel::sql_query(delete_waiter_sql(&$self.waiter_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.execute($conn)?;
+				Ok(())
+			}
+
+			fn inspect(&$self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+				let now = $self.now(config)?;
+				let row = diesel::sql_query(select_lock_sql(&$self.table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.get_result::<LockRow>($conn)
+					.optional()?;
+				row.map(LockRow::into_info).transpose()
+			}
+
+			fn provider(&$self) -> &'static str {
+				"diesel"
+			}
+		}
+
+		impl LockAdmin for DieselDriver<$client> {
+			fn list_locks(&$self) -> LockResult<Vec<LockInfo>> {
+				diesel::sql_query(select_locks_sql(&$self.table))
+					.bind::<BigInt, _>(Utc::now().timestamp_millis())
+					.load::<LockRow>($conn)?
+					.into_iter()
+					.map(LockRow::into_info)
+					.collect()
+			}
+
+			fn force_unlock(&$self, name: &str) -> LockResult<bool> {
+				let now = Utc::now().timestamp_millis();
+				let count = diesel::sql_query(force_release_lock_sql(&$self.table))
+					.bind::<BigInt, _>(now)
+					.bind::<VarChar, _>(name)
+					.bind::<BigInt, _>(now)
+					.execute($conn)?;
+				Ok(count > 0)
+			}
+		}
+
+		impl RwLockable for DieselDriver<$client> {
+			fn acquire_rw_lock(&$self, config: &LockConfig, mode: RwMode) -> LockResult<LockState> {
+				let now = config.now();
+				let until = now + config.max_lock;
+				diesel::sql_query(delete_expired_sql(&$self.rw_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)?;
+
+				let inserted = match diesel::sql_query(insert_rw_sql(&$self.rw_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(mode.as_str())
+					.bind::<VarChar, _>(config.owner().id())
+					.bind::<BigInt, _>(until.timestamp_millis())
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)
+				{
+					Ok(count) => count > 0,
+					Err(_) => false,
+				};
+
+				if !inserted {
+					return Ok(LockState::unlock());
+				}
+
+				// the row is committed before the check, of two conflicting holds inserted at the
+				// same time at least one sees the other and backs off.
+				let conflicts = diesel::sql_query(count_rw_conflict_sql(&$self.rw_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.bind::<VarChar, _>(mode.as_str())
+					.get_result::<HoldCount>($conn)?;
+				if conflicts.holds > 0 {
+					diesel::sql_query(delete_rw_sql(&$self.rw_table))
+						.bind::<VarChar, _>(&config.name)
+						.bind::<VarChar, _>(mode.as_str())
+						.bind::<VarChar, _>(config.owner().id())
+						.execute($conn)?;
+					return Ok(LockState::unlock());
+				}
+
+				Ok(LockState::new(true, now))
+			}
+
+			fn release_rw_lock(
+				&$self,
+				config: &LockConfig,
+				mode: RwMode,
+				state: &LockState,
+			) -> LockResult<LockState> {
+				let lock_until = config.lock_at_least_until(state.locked_at);
+				diesel::sql_query(release_rw_sql(&$self.rw_table))
+					.bind::<BigInt, _>(lock_until.timestamp_millis())
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(mode.as_str())
+					.bind::<VarChar, _>(config.owner().id())
+					.execute($conn)?;
+				Ok(LockState::unlock())
+			}
+
+			fn extend_rw_lock(&$self, config: &LockConfig, mode: RwMode) -> LockResult<LockState> {
+				let now = config.now();
+				let until = now + config.max_lock;
+				let count = diesel::sql_query(extend_rw_sql(&$self.rw_table))
+					.bind::<BigInt, _>(until.timestamp_millis())
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(mode.as_str())
+					.bind::<VarChar, _>(config.owner().id())
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)?;
+
+				Ok(LockState::new(count > 0, now))
+			}
+		}
+
+		/// Locks every name of a `MultiLock` in one transaction, a name held by someone else rolls
+		/// back the names locked before it. The lock name of the driver is not used.
+		impl MultiLockable for DieselDriver<$client> {
+			fn acquire_locks(&$self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+				let now = configs.first().map_or_else(Utc::now, LockConfig::now);
+				let result = ($conn).transaction::<_, LockError, _>(|conn| {
+					let mut states = Vec::with_capacity(configs.len());
+					for config in configs {
+						let until = now + config.max_lock;
+						// a savepoint keeps a failed insert from aborting the whole transaction
+						let inserted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+							diesel::sql_query(insert_lock_sql(&$self.table))
+								.bind::<VarChar, _>(&config.name)
+								.bind::<BigInt, _>(until.timestamp_millis())
+								.bind::<BigInt, _>(now.timestamp_millis())
+								.bind::<VarChar, _>(config.owner().id())
+								.execute(conn)
+						});
+
+						let locked = matches!(inserted, Ok(count) if count > 0)
+							|| diesel::sql_query(update_lock_sql(&$self.table))
+								.bind::<BigInt, _>(until.timestamp_millis())
+								.bind::<BigInt, _>(now.timestamp_millis())
+								.bind::<VarChar, _>(config.owner().id())
+								.bind::<VarChar, _>(&config.name)
+								.bind::<BigInt, _>(now.timestamp_millis())
+								.execute(conn)? > 0;
+						if !locked {
+							return Err(LockError::LockFailed);
+						}
+
+						let hold = diesel::sql_query(select_hold_sql(&$self.table))
+							.bind::<VarChar, _>(&config.name)
+							.bind::<VarChar, _>(config.owner().id())
+							.get_result::<LockHold>(conn)?;
+						states.push(LockState::new(true, now).with_token(hold.version));
+					}
+					Ok(states)
+				});
+
+				match result {
+					Err(LockError::LockFailed) => Ok(vec![LockState::unlock(); configs.len()]),
+					result => result,
+				}
+			}
+
+			fn release_locks(
+				&$self,
+				configs: &[LockConfig],
+				states: &[LockState],
+			) -> LockResult<Vec<LockState>> {
+				($conn).transaction::<_, LockError, _>(|conn| {
+					for (config, state) in configs.iter().zip(states) {
+						let lock_until = config.lock_at_least_until(state.locked_at);
+						diesel::sql_query(release_lock_sql(&$self.table))
+							.bind::<BigInt, _>(lock_until.timestamp_millis())
+							.bind::<VarChar, _>(&config.name)
+							.bind::<VarChar, _>(config.owner().id())
+							.execute(conn)?;
+					}
+					Ok(vec![LockState::unlock(); configs.len()])
+				})
+			}
+
+			fn extend_locks(&$self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+				let now = configs.first().map_or_else(Utc::now, LockConfig::now);
+				($conn).transaction::<_, LockError, _>(|conn| {
+					let mut states = Vec::with_capacity(configs.len());
+					for config in configs {
+						let until = now + config.max_lock;
+						let hold = diesel::sql_query(extend_lock_sql(&$self.table))
+							.bind::<BigInt, _>(until.timestamp_millis())
+							.bind::<VarChar, _>(&config.name)
+							.bind::<VarChar, _>(config.owner().id())
+							.bind::<BigInt, _>(now.timestamp_millis())
+							.execute(conn)?;
+						let state = match hold {
+							0 => LockState::unlock(),
+							_ => diesel::sql_query(select_hold_sql(&$self.table))
+								.bind::<VarChar, _>(&config.name)
+								.bind::<VarChar, _>(config.owner().id())
+								.get_result::<LockHold>(conn)
+								.map(|hold| LockState::new(true, now).with_token(hold.version))?,
+						};
+						states.push(state);
+					}
+					Ok(states)
+				})
+			}
+		}
+
+		impl SemaphoreLockable for DieselDriver<$client> {
+			fn acquire_permit(&$self, config: &LockConfig, permits: u32) -> LockResult<LockState> {
+				let now = config.now();
+				let until = now + config.max_lock;
+				diesel::sql_query(delete_expired_sql(&$self.semaphore_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)?;
+
+				let inserted = match diesel::sql_query(insert_permit_sql(&$self.semaphore_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.bind::<BigInt, _>(until.timestamp_millis())
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)
+				{
+					Ok(count) => count > 0,
+					Err(_) => false,
+				};
+
+				if !inserted {
+					return Ok(LockState::unlock());
+				}
+
+				// same as read-write holds: insert first, give the permit back if over the limit.
+				let taken = diesel::sql_query(count_permit_sql(&$self.semaphore_table))
+					.bind::<VarChar, _>(&config.name)
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.get_result::<HoldCount>($conn)?;
+				if taken.holds > permits as i64 {
+					diesel::sql_query(delete_permit_sql(&$self.semaphore_table))
+						.bind::<VarChar, _>(&config.name)
+						.bind::<VarChar, _>(config.owner().id())
+						.execute($conn)?;
+					return Ok(LockState::unlock());
+				}
+
+				Ok(LockState::new(true, now))
+			}
+
+			fn release_permit(&$self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+				let lock_until = config.lock_at_least_until(state.locked_at);
+				diesel::sql_query(release_permit_sql(&$self.semaphore_table))
+					.bind::<BigInt, _>(lock_until.timestamp_millis())
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.execute($conn)?;
+				Ok(LockState::unlock())
+			}
+
+			fn extend_permit(&$self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+				let now = config.now();
+				let until = now + config.max_lock;
+				let count = diesel::sql_query(extend_permit_sql(&$self.semaphore_table))
+					.bind::<BigInt, _>(until.timestamp_millis())
+					.bind::<VarChar, _>(&config.name)
+					.bind::<VarChar, _>(config.owner().id())
+					.bind::<BigInt, _>(now.timestamp_millis())
+					.execute($conn)?;
+
+				if count == 0 {
+					return Ok(LockState::unlock());
+				}
+
+				Ok(LockState::new(true, now).with_token(state.token))
 			}
 		}
 	};
 }
 
 #[cfg(feature = "diesel_sqlite")]
-impl_lockable_diesel!(::diesel::SqliteConnection, self, &mut *self.transport.borrow_mut());
+impl_lockable_diesel!(
+	::diesel::SqliteConnection,
+	self,
+	&mut *self.lock_transport()?,
+	SQLITE_NOW_SQL
+);
 #[cfg(feature = "diesel_postgres")]
-impl_lockable_diesel!(::diesel::PgConnection, self, &mut *self.transport.borrow_mut());
+impl_lockable_diesel!(::diesel::PgConnection, self, &mut *self.lock_transport()?, POSTGRES_NOW_SQL);
 #[cfg(feature = "diesel_mysql")]
-impl_lockable_diesel!(::diesel::MysqlConnection, self, &mut *self.transport.borrow_mut());
+impl_lockable_diesel!(::diesel::MysqlConnection, self, &mut *self.lock_transport()?, MYSQL_NOW_SQL);
 #[cfg(feature = "diesel_sqlite_r2d2")]
 impl_lockable_diesel!(
 	::r2d2::Pool<::diesel::r2d2::ConnectionManager<::diesel::SqliteConnection>>,
 	self,
-	&mut self.transport.borrow().get()?
+	&mut self.pool()?.get()?,
+	SQLITE_NOW_SQL
 );
 #[cfg(feature = "diesel_postgres_r2d2")]
 impl_lockable_diesel!(
 	::r2d2::Pool<::diesel::r2d2::ConnectionManager<::diesel::PgConnection>>,
 	self,
-	&mut self.transport.borrow().get()?
+	&mut self.pool()?.get()?,
+	POSTGRES_NOW_SQL
 );
 #[cfg(feature = "diesel_mysql_r2d2")]
 impl_lockable_diesel!(
 	::r2d2::Pool<::diesel::r2d2::ConnectionManager<::diesel::MysqlConnection>>,
 	self,
-	&mut self.transport.borrow().get()?
+	&mut self.pool()?.get()?,
+	MYSQL_NOW_SQL
 );
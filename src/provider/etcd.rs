@@ -0,0 +1,418 @@
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use etcd_client::Client;
+use etcd_client::Compare;
+use etcd_client::CompareOp;
+use etcd_client::DeleteOptions;
+use etcd_client::GetOptions;
+use etcd_client::KeyValue;
+use etcd_client::PutOptions;
+use etcd_client::SortOrder;
+use etcd_client::SortTarget;
+use etcd_client::Txn;
+use etcd_client::TxnOp;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+
+use crate::admin::AsyncLockAdmin;
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+use crate::core::AsyncLockable;
+use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockOwner;
+use crate::core::LockState;
+use crate::core::Lockable;
+use crate::error::ConfigError;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::telemetry::event;
+
+use super::help::from_millis;
+
+const LOCK_PREFIX: &str = "dist_lock/lock/";
+const WAITER_PREFIX: &str = "dist_lock/waiter/";
+
+/// Stores the lock in `dist_lock/lock/{name}` as a `locked_at,holds,owner` value attached to a
+/// lease of `max_lock`, so etcd deletes it once the lease runs out. The key is only created if it
+/// doesn't exist, its create revision is the fencing token. Waiters of a fair lock are kept in
+/// `dist_lock/waiter/{name}/{owner}` with a lease of the queue timeout, in create revision order.
+///
+/// Lease TTLs are whole seconds: a `max_lock`, `min_lock` or queue timeout below one second fails
+/// the acquire with `ConfigError::SubSecondLease`, longer ones are rounded up, and etcd raises TTLs
+/// below its minimum, about 1.5 seconds with the default election timeout.
+///
+/// The blocking `Lockable` impl runs the calls on the tokio runtime the driver was created on, or
+/// the one set with `with_runtime`, which has to be a multi-thread runtime when called from one of
+/// its workers. `AsyncLockAdmin` calls read the time from the clock of the driver, `SystemClock`
+/// unless set with `with_clock`.
+pub struct EtcdDriver<'a> {
+	name: String,
+	key: String,
+	transport: &'a Client,
+	runtime: Option<Handle>,
+	clock: Arc<dyn Clock>,
+}
+
+impl<'a> EtcdDriver<'a> {
+	pub fn new(lock_name: &str, transport: &'a Client) -> Self {
+		EtcdDriver {
+			name: lock_name.to_owned(),
+			key: lock_key(lock_name),
+			transport,
+			runtime: Handle::try_current().ok(),
+			clock: Arc::new(SystemClock),
+		}
+	}
+
+	/// Runtime of the blocking `Lockable` calls, should be the one the client was connected on.
+	pub fn with_runtime(mut self, runtime: Handle) -> Self {
+		self.runtime = Some(runtime);
+		self
+	}
+
+	/// Clock of the `AsyncLockAdmin` calls, should be the clock of the configs locking through
+	/// this driver.
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Run `future` to completion for the blocking `Lockable` impl.
+	fn block_on<F: Future>(&self, future: F) -> LockResult<F::Output> {
+		let Some(runtime) = &self.runtime else {
+			return Err(LockError::InvalidLock("etcd driver has no tokio runtime".to_string()));
+		};
+
+		Ok(match Handle::try_current() {
+			// a worker can't block on its own runtime without handing its tasks over first
+			Ok(_) => tokio::task::block_in_place(|| runtime.block_on(future)),
+			Err(_) => runtime.block_on(future),
+		})
+	}
+
+	/// A client of its own, the etcd calls need `&mut` and clones share the connection.
+	fn client(&self) -> Client {
+		self.transport.clone()
+	}
+
+	fn waiter_key(&self, config: &LockConfig) -> String {
+		format!("{}{}/{}", WAITER_PREFIX, self.name, config.owner().id())
+	}
+
+	async fn get(&self, client: &mut Client) -> LockResult<Option<KeyValue>> {
+		Ok(client.get(self.key.as_str(), None).await?.kvs().first().cloned())
+	}
+
+	/// Put the lock if the key doesn't exist, its create revision is the fencing token.
+	async fn create(&self, client: &mut Client, config: &LockConfig) -> LockResult<LockState> {
+		let lease = client.lease_grant(ttl_secs(config.max_lock), None).await?.id();
+		let now = config.now();
+		let value = format_value(now, 1, config.owner());
+		let txn = Txn::new()
+			.when([Compare::create_revision(self.key.as_str(), CompareOp::Equal, 0)])
+			.and_then([TxnOp::put(
+				self.key.as_str(),
+				value,
+				Some(PutOptions::new().with_lease(lease)),
+			)]);
+		let resp = client.txn(txn).await?;
+		if !resp.succeeded() {
+			client.lease_revoke(lease).await?;
+			return Ok(LockState::unlock());
+		}
+
+		let token = resp.header().map_or(0, |header| header.revision());
+		Ok(LockState::new(true, now).with_token(token))
+	}
+
+	/// Replace the lock if it wasn't modified since `kv` was read, keeping it on `lease`.
+	async fn replace(
+		&self,
+		client: &mut Client,
+		kv: &KeyValue,
+		value: String,
+		lease: i64,
+	) -> LockResult<bool> {
+		let txn = Txn::new()
+			.when([Compare::mod_revision(self.key.as_str(), CompareOp::Equal, kv.mod_revision())])
+			.and_then([TxnOp::put(
+				self.key.as_str(),
+				value,
+				Some(PutOptions::new().with_lease(lease)),
+			)]);
+		Ok(client.txn(txn).await?.succeeded())
+	}
+
+	/// Delete the lock if it wasn't modified since `kv` was read.
+	async fn delete(&self, client: &mut Client, kv: &KeyValue) -> LockResult<bool> {
+		let txn = Txn::new()
+			.when([Compare::mod_revision(self.key.as_str(), CompareOp::Equal, kv.mod_revision())])
+			.and_then([TxnOp::delete(self.key.as_str(), None)]);
+		Ok(client.txn(txn).await?.succeeded())
+	}
+
+	/// Queue the owner of `config` for its fair lock, `true` if it is the head of the queue.
+	async fn enqueue(
+		&self,
+		client: &mut Client,
+		config: &LockConfig,
+		queue_timeout: Duration,
+	) -> LockResult<bool> {
+		let waiter_key = self.waiter_key(config);
+		let waiter = client.get(waiter_key.as_str(), None).await?.kvs().first().cloned();
+		let queued = match waiter {
+			Some(waiter) => keep_alive(client, waiter.lease()).await?,
+			None => false,
+		};
+		if !queued {
+			let lease = client.lease_grant(ttl_secs(queue_timeout), None).await?.id();
+			let options = PutOptions::new().with_lease(lease);
+			client.put(waiter_key.as_str(), self.name.as_str(), Some(options)).await?;
+		}
+
+		// a waiter of a lock named `{name}/...` shares the prefix, its value tells them apart
+		let options =
+			GetOptions::new().with_prefix().with_sort(SortTarget::Create, SortOrder::Ascend);
+		let waiters = client.get(format!("{}{}/", WAITER_PREFIX, self.name), Some(options)).await?;
+		let head = waiters.kvs().iter().find(|waiter| waiter.value() == self.name.as_bytes());
+		Ok(head.is_some_and(|head| head.key() == waiter_key.as_bytes()))
+	}
+}
+
+fn lock_key(lock_name: &str) -> String {
+	format!("{}{}", LOCK_PREFIX, lock_name)
+}
+
+/// TTL of a lease in whole seconds, at least one.
+fn ttl_secs(duration: Duration) -> i64 {
+	((duration.num_milliseconds() + 999) / 1000).max(1)
+}
+
+/// Reject the lease durations of `config` that a TTL of whole seconds would stretch to a second.
+fn check_leases(config: &LockConfig) -> LockResult<()> {
+	let leases = [Some(config.max_lock), Some(config.min_lock), config.queue_timeout];
+	let stretched = |lease: &Duration| *lease > Duration::zero() && *lease < Duration::seconds(1);
+	match leases.into_iter().flatten().find(stretched) {
+		Some(lease) => Err(ConfigError::SubSecondLease(lease).into()),
+		None => Ok(()),
+	}
+}
+
+fn format_value(locked_at: DateTime<Utc>, holds: u32, owner: &LockOwner) -> String {
+	format!("{},{},{}", locked_at.timestamp_millis(), holds, owner.id())
+}
+
+/// Acquisition time, hold count and owner of a lock value.
+fn parse_value(kv: &KeyValue) -> LockResult<(DateTime<Utc>, u32, LockOwner)> {
+	let invalid =
+		|| LockError::InvalidLock(format!("invalid etcd lock value: {:?}", kv.value_str()));
+	let mut parts = kv.value_str()?.splitn(3, ',');
+	let (Some(locked_at), Some(holds), Some(owner)) = (parts.next(), parts.next(), parts.next())
+	else {
+		return Err(invalid());
+	};
+
+	let locked_at = from_millis(locked_at.parse().map_err(|_| invalid())?)?;
+	let holds = holds.parse().map_err(|_| invalid())?;
+	Ok((locked_at, holds, LockOwner::from_id(owner.to_owned())))
+}
+
+/// Renew `lease` to its granted TTL, `false` if it already expired.
+async fn keep_alive(client: &mut Client, lease: i64) -> LockResult<bool> {
+	let (mut keeper, mut stream) = client.lease_keep_alive(lease).await?;
+	keeper.keep_alive().await?;
+	Ok(stream.message().await?.is_some_and(|resp| resp.ttl() > 0))
+}
+
+/// The lock stored in `kv`, `None` if its lease expired.
+async fn lock_info(
+	client: &mut Client,
+	name: &str,
+	kv: &KeyValue,
+	now: DateTime<Utc>,
+) -> LockResult<Option<LockInfo>> {
+	let ttl = client.lease_time_to_live(kv.lease(), None).await?.ttl();
+	if ttl <= 0 {
+		return Ok(None);
+	}
+
+	let (locked_at, holds, owner) = parse_value(kv)?;
+	let info = LockInfo::new(name, owner, locked_at, now + Duration::seconds(ttl));
+	Ok(Some(info.with_token(kv.create_revision()).with_holds(holds)))
+}
+
+#[async_trait::async_trait]
+impl AsyncLockable for EtcdDriver<'_> {
+	async fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+		check_leases(config)?;
+		let mut client = self.client();
+		let queue_timeout = config.queue_timeout.filter(|_| !config.reentrant);
+		if let Some(queue_timeout) = queue_timeout {
+			if !self.enqueue(&mut client, config, queue_timeout).await? {
+				return Ok(LockState::unlock());
+			}
+		}
+
+		if config.reentrant {
+			if let Some(kv) = self.get(&mut client).await? {
+				let (locked_at, holds, owner) = parse_value(&kv)?;
+				if &owner != config.owner() {
+					return Ok(LockState::unlock());
+				}
+
+				// a lock kept only by its at least hold counts as a new acquisition
+				if holds == 0 {
+					event!(lock = %self.name, "replace the at least hold of the owner");
+					self.delete(&mut client, &kv).await?;
+				} else {
+					let value = format_value(locked_at, holds + 1, &owner);
+					if !self.replace(&mut client, &kv, value, kv.lease()).await?
+						|| !keep_alive(&mut client, kv.lease()).await?
+					{
+						return Ok(LockState::unlock());
+					}
+
+					let state = LockState::new(true, config.now()).with_token(kv.create_revision());
+					return Ok(state.with_holds(holds + 1));
+				}
+			}
+		}
+
+		let state = self.create(&mut client, config).await?;
+		if state.is_locked && queue_timeout.is_some() {
+			client.delete(self.waiter_key(config), None).await?;
+		}
+		Ok(state)
+	}
+
+	async fn release_lock(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+		let mut client = self.client();
+		let Some(kv) = self.get(&mut client).await? else {
+			return Ok(LockState::unlock());
+		};
+
+		let (locked_at, holds, owner) = parse_value(&kv)?;
+		if &owner != config.owner() {
+			return Err(LockError::NotOwner(config.name.clone()));
+		}
+
+		if config.reentrant && holds > 1 {
+			let value = format_value(locked_at, holds - 1, &owner);
+			if self.replace(&mut client, &kv, value, kv.lease()).await? {
+				return Ok(state.with_holds(holds - 1));
+			}
+			return Ok(LockState::unlock());
+		}
+
+		// keep the lock on a lease of the remaining at least hold, the revision check makes sure
+		// it is still the lock that was read
+		let remaining = config.at_least_remaining(state.locked_at);
+		if remaining > Duration::zero() {
+			let lease = client.lease_grant(ttl_secs(remaining), None).await?.id();
+			let value = format_value(locked_at, 0, &owner);
+			if !self.replace(&mut client, &kv, value, lease).await? {
+				client.lease_revoke(lease).await?;
+				return Ok(LockState::unlock());
+			}
+		} else if !self.delete(&mut client, &kv).await? {
+			return Ok(LockState::unlock());
+		}
+
+		client.lease_revoke(kv.lease()).await?;
+		Ok(LockState::unlock())
+	}
+
+	async fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+		let mut client = self.client();
+		let Some(kv) = self.get(&mut client).await? else {
+			return Ok(LockState::unlock());
+		};
+
+		let (_, holds, owner) = parse_value(&kv)?;
+		if &owner != config.owner() {
+			return Err(LockError::NotOwner(config.name.clone()));
+		}
+
+		if !keep_alive(&mut client, kv.lease()).await? {
+			return Ok(LockState::unlock());
+		}
+		Ok(LockState::new(true, config.now()).with_token(kv.create_revision()).with_holds(holds))
+	}
+
+	async fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+		self.client().delete(self.waiter_key(config), None).await?;
+		Ok(())
+	}
+
+	async fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+		let mut client = self.client();
+		match self.get(&mut client).await? {
+			Some(kv) => lock_info(&mut client, &self.name, &kv, config.now()).await,
+			None => Ok(None),
+		}
+	}
+
+	fn provider(&self) -> &'static str {
+		"etcd"
+	}
+}
+
+impl Lockable for EtcdDriver<'_> {
+	fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+		self.block_on(AsyncLockable::acquire_lock(self, config))?
+	}
+
+	fn release_lock(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+		self.block_on(AsyncLockable::release_lock(self, config, state))?
+	}
+
+	fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+		self.block_on(AsyncLockable::extend_lock(self, config))?
+	}
+
+	fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+		self.block_on(AsyncLockable::leave_queue(self, config))?
+	}
+
+	fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+		self.block_on(AsyncLockable::inspect(self, config))?
+	}
+
+	fn provider(&self) -> &'static str {
+		"etcd"
+	}
+}
+
+#[async_trait::async_trait]
+impl AsyncLockAdmin for EtcdDriver<'_> {
+	async fn list_locks(&self) -> LockResult<Vec<LockInfo>> {
+		let mut client = self.client();
+		let resp = client.get(LOCK_PREFIX, Some(GetOptions::new().with_prefix())).await?;
+		let now = self.clock.now();
+		let mut locks = Vec::with_capacity(resp.kvs().len());
+		for kv in resp.kvs() {
+			let Some(name) = kv.key_str()?.strip_prefix(LOCK_PREFIX) else {
+				continue;
+			};
+
+			locks.extend(lock_info(&mut client, name, kv, now).await?);
+		}
+		Ok(locks)
+	}
+
+	async fn force_unlock(&self, name: &str) -> LockResult<bool> {
+		let mut client = self.client();
+		let options = DeleteOptions::new().with_prev_key();
+		let resp = client.delete(lock_key(name), Some(options)).await?;
+		let Some(kv) = resp.prev_kvs().first() else {
+			return Ok(false);
+		};
+
+		// the key is gone, the lease only needs to be cleaned up
+		let _ = client.lease_revoke(kv.lease()).await;
+		Ok(true)
+	}
+}
@@ -0,0 +1,303 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use chrono::Utc;
+use fs2::FileExt;
+
+use crate::admin::LockAdmin;
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockOwner;
+use crate::core::LockState;
+use crate::core::Lockable;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::telemetry::event;
+
+use super::help::from_millis;
+use super::help::lease::Lease;
+use super::help::lease::Store;
+use super::help::lease::Waiter;
+
+const LOCK_FILE_EXT: &str = "lock";
+const GUARD_FILE_EXT: &str = "lck";
+const TEMP_FILE_EXT: &str = "tmp";
+
+/// Keeps every lock in a file of a directory, for processes of one host or sharing a volume.
+///
+/// A lock file holds the lock name, the last fencing token and, while the lock is held, its owner,
+/// `locked_at`, `lock_until` and hold count, followed by the waiters of a fair lock. Every call
+/// that changes a lock takes an exclusive `flock` on a `.lck` guard file next to it while it reads
+/// the lock file and replaces it, writing a temporary file and renaming it over the lock file so
+/// that a crash leaves either the old or the new content. The lease itself lives in the file
+/// content and expires at the stored `lock_until`. Locks behave like those of `MemoryDriver`, the
+/// files are kept after release so fencing tokens keep increasing.
+///
+/// Lease times come from the clocks of the hosts, so hosts sharing a volume should keep their
+/// clocks in sync within `LockConfig::drift`. NFS only supports `flock` since Linux 2.6.12, where
+/// it is emulated with byte-range locks.
+#[derive(Debug, Clone)]
+pub struct FileDriver {
+	dir: PathBuf,
+	clock: Arc<dyn Clock>,
+}
+
+impl FileDriver {
+	/// A driver keeping its lock files in `dir`, which is created if missing.
+	pub fn new<P: Into<PathBuf>>(dir: P) -> LockResult<FileDriver> {
+		let dir = dir.into();
+		fs::create_dir_all(&dir)?;
+		Ok(FileDriver { dir, clock: Arc::new(SystemClock) })
+	}
+
+	/// Clock of the `LockAdmin` calls, should be the clock of the configs locking through this
+	/// driver.
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	pub fn dir(&self) -> &Path {
+		&self.dir
+	}
+
+	/// Lock file of `name`, characters other than ASCII alphanumerics, `-`, `_` and `.` are
+	/// percent-encoded.
+	fn path(&self, name: &str) -> PathBuf {
+		let mut file_name =
+			encode(name, |c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+		file_name.push('.');
+		file_name.push_str(LOCK_FILE_EXT);
+		self.dir.join(file_name)
+	}
+
+	/// Run `f` on the lock of `name` while holding its guard file, replacing the lock file if it
+	/// changed.
+	fn update<R>(&self, name: &str, f: impl FnOnce(&mut Store) -> R) -> LockResult<R> {
+		let path = self.path(name);
+		let guard = OpenOptions::new()
+			.write(true)
+			.create(true)
+			.open(path.with_extension(GUARD_FILE_EXT))?;
+		guard.lock_exclusive()?;
+		let content = read_content(&path)?.unwrap_or_default();
+		let mut store = parse_store(name, &content, &path)?;
+		let result = f(&mut store);
+		let updated = format_store(name, &store);
+		if updated != content {
+			event!(lock = name, path = %path.display(), "rewrite the lock file");
+			let temp = path.with_extension(TEMP_FILE_EXT);
+			let mut file = File::create(&temp)?;
+			file.write_all(updated.as_bytes())?;
+			file.sync_all()?;
+			fs::rename(&temp, &path)?;
+			// persist the rename, a directory can't be synced on every platform
+			if let Ok(dir) = File::open(&self.dir) {
+				let _ = dir.sync_all();
+			}
+		}
+		// closing the guard file releases the flock
+		Ok(result)
+	}
+
+	/// The lock in the file at `path`, `None` if there is no such file. Needs no guard as the
+	/// file is only ever replaced whole.
+	fn read(&self, path: &Path) -> LockResult<Option<Store>> {
+		let Some(content) = read_content(path)? else {
+			return Ok(None);
+		};
+		let Some(name) = content.lines().find_map(|line| line.strip_prefix("name=")) else {
+			return Ok(None);
+		};
+
+		parse_store(&decode(name, path)?, &content, path).map(Some)
+	}
+}
+
+/// Content of the file at `path`, `None` if there is no such file.
+fn read_content(path: &Path) -> LockResult<Option<String>> {
+	match fs::read_to_string(path) {
+		Ok(content) => Ok(Some(content)),
+		Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e.into()),
+	}
+}
+
+/// Percent-encode the characters of `value` not accepted by `keep`, and `%` itself.
+fn encode(value: &str, keep: impl Fn(char) -> bool) -> String {
+	let mut encoded = String::with_capacity(value.len());
+	for c in value.chars() {
+		if c != '%' && keep(c) {
+			encoded.push(c);
+		} else {
+			let mut buf = [0; 4];
+			c.encode_utf8(&mut buf).bytes().for_each(|b| {
+				let _ = write!(encoded, "%{:02X}", b);
+			});
+		}
+	}
+	encoded
+}
+
+/// Value of a line of a lock file, which has line breaks percent-encoded.
+fn encode_value(value: &str) -> String {
+	encode(value, |c| !matches!(c, '\n' | '\r'))
+}
+
+/// Reverse of `encode`, `path` is the file the value was read from.
+fn decode(value: &str, path: &Path) -> LockResult<String> {
+	let invalid =
+		|| LockError::InvalidLock(format!("invalid escape in {}: {}", path.display(), value));
+	let mut bytes = Vec::with_capacity(value.len());
+	let mut rest = value.as_bytes();
+	while let Some((&b, tail)) = rest.split_first() {
+		rest = tail;
+		if b != b'%' {
+			bytes.push(b);
+			continue;
+		}
+
+		let digit = |b: &u8| (*b as char).to_digit(16);
+		let [hi, lo, tail @ ..] = rest else {
+			return Err(invalid());
+		};
+		let (Some(hi), Some(lo)) = (digit(hi), digit(lo)) else {
+			return Err(invalid());
+		};
+		bytes.push((hi * 16 + lo) as u8);
+		rest = tail;
+	}
+	String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+/// Load the lock of `name` from the content of its file, an empty file is a lock never taken.
+/// Values are percent-encoded, see `encode_value`.
+fn parse_store(name: &str, content: &str, path: &Path) -> LockResult<Store> {
+	let invalid = |line: &str| {
+		LockError::InvalidLock(format!("invalid line in {}: {}", path.display(), line))
+	};
+	let millis = |line: &str, value: &str| -> LockResult<DateTime<Utc>> {
+		from_millis(value.parse().map_err(|_| invalid(line))?)
+	};
+
+	let mut store = Store::default();
+	let (mut owner, mut locked_at, mut lock_until, mut holds) = (None, None, None, 1);
+	let mut token = 0;
+	let mut waiters = Vec::new();
+	for line in content.lines() {
+		let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+		match key {
+			"name" if decode(value, path)? == name => {}
+			"token" => token = value.parse().map_err(|_| invalid(line))?,
+			"owner" => owner = Some(LockOwner::from_id(decode(value, path)?)),
+			"locked_at" => locked_at = Some(millis(line, value)?),
+			"lock_until" => lock_until = Some(millis(line, value)?),
+			"holds" => holds = value.parse().map_err(|_| invalid(line))?,
+			"waiter" => {
+				let (deadline, owner) = value.split_once(',').ok_or_else(|| invalid(line))?;
+				let owner = LockOwner::from_id(decode(owner, path)?);
+				waiters.push(Waiter { owner, deadline: millis(line, deadline)? });
+			}
+			_ => return Err(invalid(line)),
+		}
+	}
+
+	if token > 0 {
+		store.fences.insert(name.to_owned(), token);
+	}
+	if let (Some(owner), Some(locked_at), Some(lock_until)) = (owner, locked_at, lock_until) {
+		let lease = Lease { owner, locked_at, lock_until, holds, token };
+		store.leases.insert(name.to_owned(), lease);
+	}
+	if !waiters.is_empty() {
+		store.queues.insert(name.to_owned(), waiters);
+	}
+	Ok(store)
+}
+
+/// Content of the lock file of `name`, one `key=value` per line.
+fn format_store(name: &str, store: &Store) -> String {
+	let mut content = format!("name={}\n", encode_value(name));
+	if let Some(token) = store.fences.get(name) {
+		let _ = writeln!(content, "token={}", token);
+	}
+	if let Some(lease) = store.leases.get(name) {
+		let _ = writeln!(content, "owner={}", encode_value(lease.owner.id()));
+		let _ = writeln!(content, "locked_at={}", lease.locked_at.timestamp_millis());
+		let _ = writeln!(content, "lock_until={}", lease.lock_until.timestamp_millis());
+		let _ = writeln!(content, "holds={}", lease.holds);
+	}
+	for waiter in store.queues.get(name).into_iter().flatten() {
+		let _ = writeln!(
+			content,
+			"waiter={},{}",
+			waiter.deadline.timestamp_millis(),
+			encode_value(waiter.owner.id())
+		);
+	}
+	content
+}
+
+impl Lockable for FileDriver {
+	fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+		self.update(&config.name, |store| store.acquire(config, config.now()))
+	}
+
+	fn release_lock(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+		self.update(&config.name, |store| store.release(config, state, config.now()))?
+	}
+
+	fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+		self.update(&config.name, |store| store.extend(config, config.now()))?
+	}
+
+	fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+		self.update(&config.name, |store| store.leave_queue(config))
+	}
+
+	fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+		let store = self.read(&self.path(&config.name))?;
+		Ok(store.and_then(|store| store.inspect(&config.name, config.now())))
+	}
+
+	fn provider(&self) -> &'static str {
+		"file"
+	}
+}
+
+impl LockAdmin for FileDriver {
+	fn list_locks(&self) -> LockResult<Vec<LockInfo>> {
+		let now = self.clock.now();
+		let mut locks = Vec::new();
+		for entry in fs::read_dir(&self.dir)? {
+			let path = entry?.path();
+			if path.extension().map_or(true, |ext| ext != LOCK_FILE_EXT) {
+				continue;
+			}
+
+			if let Some(store) = self.read(&path)? {
+				locks.extend(store.list(now));
+			}
+		}
+		Ok(locks)
+	}
+
+	fn force_unlock(&self, name: &str) -> LockResult<bool> {
+		if !self.path(name).exists() {
+			return Ok(false);
+		}
+
+		let now = self.clock.now();
+		self.update(name, |store| store.force_unlock(name, now))
+	}
+}
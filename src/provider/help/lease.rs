@@ -0,0 +1,178 @@
+//! Lease bookkeeping of drivers that keep their locks themselves instead of in a coordinator.
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockOwner;
+use crate::core::LockState;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::telemetry::event;
+
+#[derive(Debug)]
+pub(crate) struct Lease {
+	pub(crate) owner: LockOwner,
+	pub(crate) locked_at: DateTime<Utc>,
+	pub(crate) lock_until: DateTime<Utc>,
+	pub(crate) holds: u32,
+	pub(crate) token: i64,
+}
+
+#[derive(Debug)]
+pub(crate) struct Waiter {
+	pub(crate) owner: LockOwner,
+	pub(crate) deadline: DateTime<Utc>,
+}
+
+/// Locks by name, `MemoryDriver` keeps every lock in one store, `FileDriver` loads the lock it
+/// works on from its file.
+#[derive(Debug, Default)]
+pub(crate) struct Store {
+	pub(crate) leases: HashMap<String, Lease>,
+	/// Last fencing token of every lock name, kept when the lease is gone.
+	pub(crate) fences: HashMap<String, i64>,
+	/// Waiters of fair locks in arrival order.
+	pub(crate) queues: HashMap<String, Vec<Waiter>>,
+}
+
+impl Store {
+	pub(crate) fn acquire(&mut self, config: &LockConfig, now: DateTime<Utc>) -> LockState {
+		let queue_timeout = config.queue_timeout.filter(|_| !config.reentrant);
+		if let Some(queue_timeout) = queue_timeout {
+			if !self.enqueue(config, now, queue_timeout) {
+				return LockState::unlock();
+			}
+		}
+
+		let until = now + config.max_lock;
+		let lease = self.leases.get_mut(&config.name);
+		if let Some(lease) = lease.filter(|lease| lease.lock_until > now - config.drift) {
+			if !config.reentrant || &lease.owner != config.owner() {
+				return LockState::unlock();
+			}
+
+			// a lock kept only by its at least hold counts as a new acquisition
+			if lease.holds > 0 {
+				lease.holds += 1;
+				lease.lock_until = until;
+				return LockState::new(true, now).with_token(lease.token).with_holds(lease.holds);
+			}
+		}
+
+		let token = self.fences.entry(config.name.clone()).or_default();
+		*token += 1;
+		let lease = Lease {
+			owner: config.owner().clone(),
+			locked_at: now,
+			lock_until: until,
+			holds: 1,
+			token: *token,
+		};
+		let state = LockState::new(true, now).with_token(lease.token);
+		self.leases.insert(config.name.clone(), lease);
+		if queue_timeout.is_some() {
+			self.leave_queue(config);
+		}
+		state
+	}
+
+	pub(crate) fn release(
+		&mut self,
+		config: &LockConfig,
+		state: &LockState,
+		now: DateTime<Utc>,
+	) -> LockResult<LockState> {
+		let Some(lease) = self.live_lease(&config.name, now) else {
+			return Ok(LockState::unlock());
+		};
+
+		if &lease.owner != config.owner() {
+			return Err(LockError::NotOwner(config.name.clone()));
+		}
+
+		if config.reentrant && lease.holds > 1 {
+			lease.holds -= 1;
+			return Ok(state.with_holds(lease.holds));
+		}
+
+		let remaining = config.at_least_remaining(state.locked_at);
+		if remaining > Duration::zero() {
+			lease.lock_until = now + remaining;
+			lease.holds = 0;
+		} else {
+			self.leases.remove(&config.name);
+		}
+		Ok(LockState::unlock())
+	}
+
+	pub(crate) fn extend(
+		&mut self,
+		config: &LockConfig,
+		now: DateTime<Utc>,
+	) -> LockResult<LockState> {
+		let Some(lease) = self.live_lease(&config.name, now) else {
+			return Ok(LockState::unlock());
+		};
+
+		if &lease.owner != config.owner() {
+			return Err(LockError::NotOwner(config.name.clone()));
+		}
+
+		lease.locked_at = now;
+		lease.lock_until = now + config.max_lock;
+		Ok(LockState::new(true, now).with_token(lease.token).with_holds(lease.holds))
+	}
+
+	/// Queue the owner of `config` for its fair lock, `true` if it is the head of the queue.
+	fn enqueue(
+		&mut self,
+		config: &LockConfig,
+		now: DateTime<Utc>,
+		queue_timeout: Duration,
+	) -> bool {
+		let queue = self.queues.entry(config.name.clone()).or_default();
+		queue.retain(|waiter| waiter.deadline > now);
+		let deadline = now + queue_timeout;
+		match queue.iter_mut().find(|waiter| &waiter.owner == config.owner()) {
+			Some(waiter) => waiter.deadline = deadline,
+			None => queue.push(Waiter { owner: config.owner().clone(), deadline }),
+		}
+		queue.first().is_some_and(|waiter| &waiter.owner == config.owner())
+	}
+
+	pub(crate) fn leave_queue(&mut self, config: &LockConfig) {
+		if let Some(queue) = self.queues.get_mut(&config.name) {
+			queue.retain(|waiter| &waiter.owner != config.owner());
+			if queue.is_empty() {
+				self.queues.remove(&config.name);
+			}
+		}
+	}
+
+	/// The unexpired lease of `name`, an expired one is dropped.
+	fn live_lease(&mut self, name: &str, now: DateTime<Utc>) -> Option<&mut Lease> {
+		if self.leases.get(name).is_some_and(|lease| lease.lock_until <= now) {
+			event!(lock = name, "dropped an expired lease");
+			self.leases.remove(name);
+		}
+		self.leases.get_mut(name)
+	}
+
+	pub(crate) fn inspect(&self, name: &str, now: DateTime<Utc>) -> Option<LockInfo> {
+		let lease = self.leases.get(name).filter(|lease| lease.lock_until > now)?;
+		let info = LockInfo::new(name, lease.owner.clone(), lease.locked_at, lease.lock_until);
+		Some(info.with_token(lease.token).with_holds(lease.holds))
+	}
+
+	pub(crate) fn list(&self, now: DateTime<Utc>) -> Vec<LockInfo> {
+		self.leases.keys().filter_map(|name| self.inspect(name, now)).collect()
+	}
+
+	pub(crate) fn force_unlock(&mut self, name: &str, now: DateTime<Utc>) -> bool {
+		self.live_lease(name, now).is_some() && self.leases.remove(name).is_some()
+	}
+}
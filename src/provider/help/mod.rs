@@ -1,2 +1,26 @@
+#[cfg(any(feature = "memory", feature = "file"))]
+pub(crate) mod lease;
 #[cfg(feature = "diesel")]
 pub(crate) mod sql_stmt;
+
+cfg_if::cfg_if! {
+	if #[cfg(any(
+		feature = "diesel",
+		feature = "redis",
+		feature = "zookeeper",
+		feature = "file",
+		feature = "etcd"
+	))] {
+		use chrono::DateTime;
+		use chrono::Utc;
+
+		use crate::error::LockError;
+		use crate::error::LockResult;
+
+		/// Time of a timestamp in milliseconds as stored by the drivers.
+		pub(crate) fn from_millis(ts: i64) -> LockResult<DateTime<Utc>> {
+			DateTime::from_timestamp(ts.div_euclid(1000), (ts.rem_euclid(1000) * 1_000_000) as u32)
+				.ok_or(LockError::InvalidLock(format!("convert ts: {} to DateTime failed", ts)))
+		}
+	}
+}
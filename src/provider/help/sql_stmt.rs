@@ -1,5 +1,14 @@
 use std::fmt::Display;
 
+/// Current time of the database in epoch millis, selected as `now`.
+#[cfg(feature = "diesel_sqlite")]
+pub const SQLITE_NOW_SQL: &str =
+	"SELECT CAST((julianday('now') - 2440587.5) * 86400000 AS BIGINT) AS now";
+#[cfg(feature = "diesel_postgres")]
+pub const POSTGRES_NOW_SQL: &str = "SELECT CAST(EXTRACT(EPOCH FROM NOW()) * 1000 AS BIGINT) AS now";
+#[cfg(feature = "diesel_mysql")]
+pub const MYSQL_NOW_SQL: &str = "SELECT CAST(UNIX_TIMESTAMP(NOW(3)) * 1000 AS SIGNED) AS now";
+
 cfg_if::cfg_if! {
 	if #[cfg(feature = "diesel_postgres")] {
 		#[inline(always)]
@@ -8,7 +17,7 @@ cfg_if::cfg_if! {
 			T: Display,
 		{
 			format!(
-				"INSERT INTO {} (name, lock_until, locked_at, locked_by) VALUES ($1, $2, $3, $4)",
+				"INSERT INTO {} (name, lock_until, locked_at, locked_by, version, holds) VALUES ($1, $2, $3, $4, 1, 1)",
 				table_name
 			)
 		}
@@ -19,7 +28,7 @@ cfg_if::cfg_if! {
 			T: Display,
 		{
 			format!(
-				"UPDATE {} SET lock_until = $1, locked_at = $2, locked_by = $3 WHERE name = $4 AND lock_until <= $5",
+				"UPDATE {} SET lock_until = $1, locked_at = $2, locked_by = $3, version = version + 1, holds = 1 WHERE name = $4 AND lock_until <= $5",
 				table_name)
 		}
 
@@ -28,7 +37,10 @@ cfg_if::cfg_if! {
 		where
 			T: Display,
 		{
-			format!("UPDATE {} SET lock_until = $1 WHERE name = $2", table_name)
+			format!(
+				"UPDATE {} SET lock_until = $1, holds = 0 WHERE name = $2 AND locked_by = $3",
+				table_name
+			)
 		}
 
 		#[inline(always)]
@@ -41,6 +53,239 @@ cfg_if::cfg_if! {
 				table_name
 			)
 		}
+
+		#[inline(always)]
+		pub fn select_hold_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!("SELECT version, holds FROM {} WHERE name = $1 AND locked_by = $2", table_name)
+		}
+
+		#[inline(always)]
+		pub fn select_lock_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT name, locked_by, locked_at, lock_until, version, holds FROM {} WHERE name = $1 AND lock_until > $2",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn select_locks_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT name, locked_by, locked_at, lock_until, version, holds FROM {} WHERE lock_until > $1",
+				table_name
+			)
+		}
+
+		/// Expire a live lock whoever holds it, the row stays to keep its version.
+		#[inline(always)]
+		pub fn force_release_lock_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = $1, holds = 0 WHERE name = $2 AND lock_until > $3",
+				table_name
+			)
+		}
+
+		/// Add a hold to a live lock of the same owner, a lock kept only by its at least hold
+		/// counts as a new acquisition and gets a new version.
+		#[inline(always)]
+		pub fn reenter_lock_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET version = CASE WHEN holds = 0 THEN version + 1 ELSE version END, lock_until = $1, holds = holds + 1 WHERE name = $2 AND locked_by = $3 AND lock_until > $4",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn release_hold_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET holds = holds - 1 WHERE name = $1 AND locked_by = $2 AND holds > 1",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_expired_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = $1 AND lock_until <= $2",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn insert_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"INSERT INTO {} (name, mode, locked_by, lock_until, locked_at) VALUES ($1, $2, $3, $4, $5)",
+				table_name
+			)
+		}
+
+		/// Count the live holds of other owners that conflict with a hold of the bound mode.
+		#[inline(always)]
+		pub fn count_rw_conflict_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT COUNT(*) AS holds FROM {} WHERE name = $1 AND locked_by <> $2 AND lock_until > $3 AND (mode = 'write' OR $4 = 'write')",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = $1 AND mode = $2 AND locked_by = $3",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn release_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = $1 WHERE name = $2 AND mode = $3 AND locked_by = $4",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn extend_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = $1 WHERE name = $2 AND mode = $3 AND locked_by = $4 AND lock_until > $5",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn insert_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"INSERT INTO {} (name, locked_by, lock_until, locked_at) VALUES ($1, $2, $3, $4)",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn count_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT COUNT(*) AS holds FROM {} WHERE name = $1 AND lock_until > $2",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = $1 AND locked_by = $2",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn release_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = $1 WHERE name = $2 AND locked_by = $3",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn extend_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = $1 WHERE name = $2 AND locked_by = $3 AND lock_until > $4",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn insert_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"INSERT INTO {} (name, locked_by, lock_until) VALUES ($1, $2, $3)",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn refresh_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = $1 WHERE name = $2 AND locked_by = $3",
+				table_name
+			)
+		}
+
+		/// The waiter first in line, waiters are ordered by their auto increment id.
+		#[inline(always)]
+		pub fn select_head_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT locked_by FROM {} WHERE name = $1 ORDER BY id LIMIT 1",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = $1 AND locked_by = $2",
+				table_name
+			)
+		}
 	} else if #[cfg(any(feature = "diesel_sqlite", feature = "diesel_mysql"))]{
 
 		#[inline(always)]
@@ -49,7 +294,7 @@ cfg_if::cfg_if! {
 			T: Display,
 		{
 			format!(
-				"INSERT INTO {} (name, lock_until, locked_at, locked_by) VALUES (?, ?, ?, ?)",
+				"INSERT INTO {} (name, lock_until, locked_at, locked_by, version, holds) VALUES (?, ?, ?, ?, 1, 1)",
 				table_name
 			)
 		}
@@ -60,7 +305,7 @@ cfg_if::cfg_if! {
 			T: Display,
 		{
 			format!(
-				"UPDATE {} SET lock_until = ?, locked_at = ?, locked_by = ? WHERE name = ? AND lock_until <= ?",
+				"UPDATE {} SET lock_until = ?, locked_at = ?, locked_by = ?, version = version + 1, holds = 1 WHERE name = ? AND lock_until <= ?",
 				table_name)
 		}
 
@@ -69,7 +314,10 @@ cfg_if::cfg_if! {
 		where
 			T: Display,
 		{
-			format!("UPDATE {} SET lock_until = ? WHERE name = ?", table_name)
+			format!(
+				"UPDATE {} SET lock_until = ?, holds = 0 WHERE name = ? AND locked_by = ?",
+				table_name
+			)
 		}
 
 		#[inline(always)]
@@ -82,6 +330,239 @@ cfg_if::cfg_if! {
 				table_name
 			)
 		}
+
+		#[inline(always)]
+		pub fn select_hold_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!("SELECT version, holds FROM {} WHERE name = ? AND locked_by = ?", table_name)
+		}
+
+		#[inline(always)]
+		pub fn select_lock_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT name, locked_by, locked_at, lock_until, version, holds FROM {} WHERE name = ? AND lock_until > ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn select_locks_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT name, locked_by, locked_at, lock_until, version, holds FROM {} WHERE lock_until > ?",
+				table_name
+			)
+		}
+
+		/// Expire a live lock whoever holds it, the row stays to keep its version.
+		#[inline(always)]
+		pub fn force_release_lock_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = ?, holds = 0 WHERE name = ? AND lock_until > ?",
+				table_name
+			)
+		}
+
+		/// Add a hold to a live lock of the same owner, a lock kept only by its at least hold
+		/// counts as a new acquisition and gets a new version.
+		#[inline(always)]
+		pub fn reenter_lock_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET version = CASE WHEN holds = 0 THEN version + 1 ELSE version END, lock_until = ?, holds = holds + 1 WHERE name = ? AND locked_by = ? AND lock_until > ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn release_hold_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET holds = holds - 1 WHERE name = ? AND locked_by = ? AND holds > 1",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_expired_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = ? AND lock_until <= ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn insert_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"INSERT INTO {} (name, mode, locked_by, lock_until, locked_at) VALUES (?, ?, ?, ?, ?)",
+				table_name
+			)
+		}
+
+		/// Count the live holds of other owners that conflict with a hold of the bound mode.
+		#[inline(always)]
+		pub fn count_rw_conflict_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT COUNT(*) AS holds FROM {} WHERE name = ? AND locked_by <> ? AND lock_until > ? AND (mode = 'write' OR ? = 'write')",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = ? AND mode = ? AND locked_by = ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn release_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = ? WHERE name = ? AND mode = ? AND locked_by = ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn extend_rw_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = ? WHERE name = ? AND mode = ? AND locked_by = ? AND lock_until > ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn insert_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"INSERT INTO {} (name, locked_by, lock_until, locked_at) VALUES (?, ?, ?, ?)",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn count_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT COUNT(*) AS holds FROM {} WHERE name = ? AND lock_until > ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = ? AND locked_by = ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn release_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = ? WHERE name = ? AND locked_by = ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn extend_permit_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = ? WHERE name = ? AND locked_by = ? AND lock_until > ?",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn insert_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"INSERT INTO {} (name, locked_by, lock_until) VALUES (?, ?, ?)",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn refresh_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"UPDATE {} SET lock_until = ? WHERE name = ? AND locked_by = ?",
+				table_name
+			)
+		}
+
+		/// The waiter first in line, waiters are ordered by their auto increment id.
+		#[inline(always)]
+		pub fn select_head_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"SELECT locked_by FROM {} WHERE name = ? ORDER BY id LIMIT 1",
+				table_name
+			)
+		}
+
+		#[inline(always)]
+		pub fn delete_waiter_sql<T>(table_name: T) -> String
+		where
+			T: Display,
+		{
+			format!(
+				"DELETE FROM {} WHERE name = ? AND locked_by = ?",
+				table_name
+			)
+		}
 	}
 
 }
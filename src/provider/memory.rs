@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::admin::AsyncLockAdmin;
+use crate::admin::LockAdmin;
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::AsyncLockable;
+use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockState;
+use crate::core::Lockable;
+use crate::error::LockResult;
+
+use super::help::lease::Store;
+
+/// Keeps locks in the memory of this process, for tests and for locking between the threads and
+/// tasks of one process. The driver is a handle to an `Arc`, its clones share the same locks.
+///
+/// Locks behave like those of the other drivers: they expire after `max_lock`, are kept until
+/// `min_lock` has passed on release, can only be extended by their owner while held, and every
+/// acquisition gets a new fencing token.
+///
+/// Lock calls read the time from the clock of their `LockConfig`, `LockAdmin` calls from the clock
+/// of the driver, `SystemClock` unless set with `with_clock`.
+#[derive(Debug, Clone)]
+pub struct MemoryDriver {
+	store: Arc<Mutex<Store>>,
+	clock: Arc<dyn Clock>,
+}
+
+impl Default for MemoryDriver {
+	fn default() -> Self {
+		MemoryDriver { store: Default::default(), clock: Arc::new(SystemClock) }
+	}
+}
+
+impl MemoryDriver {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Clock of the `LockAdmin` calls, should be the clock of the configs locking through this
+	/// driver.
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	fn store(&self) -> MutexGuard<'_, Store> {
+		self.store.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+macro_rules! impl_lockable_memory {
+	($(#[$meta: meta])* $lockable: ident, $admin: ident, $($async: ident)?) => {
+		$(#[$meta])*
+		impl $lockable for MemoryDriver {
+			$($async)? fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+				Ok(self.store().acquire(config, config.now()))
+			}
+
+			$($async)? fn release_lock(
+				&self,
+				config: &LockConfig,
+				state: &LockState,
+			) -> LockResult<LockState> {
+				self.store().release(config, state, config.now())
+			}
+
+			$($async)? fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+				self.store().extend(config, config.now())
+			}
+
+			$($async)? fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+				self.store().leave_queue(config);
+				Ok(())
+			}
+
+			$($async)? fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+				Ok(self.store().inspect(&config.name, config.now()))
+			}
+
+			fn provider(&self) -> &'static str {
+				"memory"
+			}
+		}
+
+		$(#[$meta])*
+		impl $admin for MemoryDriver {
+			$($async)? fn list_locks(&self) -> LockResult<Vec<LockInfo>> {
+				Ok(self.store().list(self.clock.now()))
+			}
+
+			$($async)? fn force_unlock(&self, name: &str) -> LockResult<bool> {
+				Ok(self.store().force_unlock(name, self.clock.now()))
+			}
+		}
+	};
+}
+
+impl_lockable_memory!(Lockable, LockAdmin,);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_lockable_memory!(
+	#[async_trait::async_trait]
+	AsyncLockable,
+	AsyncLockAdmin,
+	async
+);
@@ -1,5 +1,12 @@
 #[cfg(feature = "diesel")]
 pub mod diesel;
+#[cfg(feature = "etcd")]
+pub mod etcd;
+#[cfg(feature = "file")]
+pub mod file;
+#[cfg(feature = "memory")]
+pub mod memory;
+pub mod quorum;
 #[cfg(feature = "redis")]
 pub mod redis;
 #[cfg(feature = "zookeeper")]
@@ -10,6 +17,17 @@ mod help;
 #[cfg(feature = "diesel")]
 pub use diesel::DieselDriver;
 
+#[cfg(feature = "etcd")]
+pub use etcd::EtcdDriver;
+
+#[cfg(feature = "file")]
+pub use file::FileDriver;
+
+#[cfg(feature = "memory")]
+pub use memory::MemoryDriver;
+
+pub use quorum::QuorumDriver;
+
 #[cfg(feature = "redis")]
 pub use redis::RedisDriver;
 
@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::AsyncLockable;
+use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockState;
+use crate::core::Lockable;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::signal::LostSignal;
+
+const DEFAULT_DRIFT_FACTOR: f64 = 0.01;
+const DEFAULT_DRIFT_OFFSET_MILLIS: i64 = 2;
+
+/// A lock held on a majority of independent drivers, in the style of Redlock.
+///
+/// The lock counts as acquired when a majority of the drivers locked it and the time left of
+/// `max_lock`, minus the time spent acquiring and the clock drift, is still positive. The state
+/// is dated back by the drift so that `DistLock` treats it as expired when the validity window
+/// ends. On failure every driver that did lock it is released again.
+///
+/// Fencing tokens of independent backends can't be compared, the highest one of the majority is
+/// reported.
+#[derive(Debug)]
+pub struct QuorumDriver<T> {
+	drivers: Vec<T>,
+	drift_factor: f64,
+	drift_offset: Duration,
+}
+
+impl<T> QuorumDriver<T> {
+	pub fn new(drivers: Vec<T>) -> Self {
+		QuorumDriver {
+			drivers,
+			drift_factor: DEFAULT_DRIFT_FACTOR,
+			drift_offset: Duration::milliseconds(DEFAULT_DRIFT_OFFSET_MILLIS),
+		}
+	}
+
+	/// Clock drift allowance: `factor` of `max_lock` plus `offset`, 1% plus 2ms by default.
+	pub fn with_drift(mut self, factor: f64, offset: Duration) -> Self {
+		self.drift_factor = factor;
+		self.drift_offset = offset;
+		self
+	}
+
+	pub fn drivers(&self) -> &[T] {
+		&self.drivers
+	}
+
+	pub fn quorum(&self) -> usize {
+		self.drivers.len() / 2 + 1
+	}
+
+	fn drift(&self, config: &LockConfig) -> Duration {
+		let millis = config.max_lock.num_milliseconds() as f64 * self.drift_factor;
+		Duration::milliseconds(millis as i64) + self.drift_offset
+	}
+
+	/// Combine the driver states into the quorum state, `None` if there is no valid quorum.
+	fn quorum_state(
+		&self,
+		config: &LockConfig,
+		start: DateTime<Utc>,
+		states: &[LockResult<LockState>],
+	) -> Option<LockState> {
+		let locked = states.iter().flatten().filter(|state| state.is_locked).collect::<Vec<_>>();
+		let drift = self.drift(config);
+		let validity = config.max_lock - (config.now() - start) - drift;
+		if locked.len() < self.quorum() || validity <= Duration::zero() {
+			return None;
+		}
+
+		let token = locked.iter().map(|state| state.token).max().unwrap_or_default();
+		Some(LockState::new(true, start - drift).with_token(token))
+	}
+
+	/// Lock reported with the same owner by a majority of the drivers, the first error if no
+	/// driver answered. It lasts while a majority still holds it and carries the highest token.
+	fn quorum_info(
+		&self,
+		results: Vec<LockResult<Option<LockInfo>>>,
+	) -> LockResult<Option<LockInfo>> {
+		let infos = results.iter().flatten().flatten().collect::<Vec<_>>();
+		for info in &infos {
+			let mut agreed =
+				infos.iter().filter(|other| other.holder == info.holder).collect::<Vec<_>>();
+			if agreed.len() < self.quorum() {
+				continue;
+			}
+
+			agreed.sort_by_key(|other| Reverse(other.lock_until));
+			let mut quorum_info = (*info).clone();
+			quorum_info.lock_until = agreed[self.quorum() - 1].lock_until;
+			quorum_info.token = agreed.iter().filter_map(|other| other.token).max();
+			return Ok(Some(quorum_info));
+		}
+
+		let answered = results.iter().any(Result::is_ok);
+		match results.into_iter().find_map(Result::err) {
+			Some(e) if !answered => Err(e),
+			_ => Ok(None),
+		}
+	}
+
+	/// Result of releasing on every driver: fine if a majority succeeded, otherwise the first
+	/// error.
+	fn quorum_release(&self, results: Vec<LockResult<LockState>>) -> LockResult<LockState> {
+		let released = results.iter().filter(|result| result.is_ok()).count();
+		match results.into_iter().find_map(Result::err) {
+			Some(e) if released < self.quorum() => Err(e),
+			_ => Ok(LockState::unlock()),
+		}
+	}
+}
+
+macro_rules! impl_lockable_quorum {
+	($(#[$meta: meta])* $lockable: ident, $($bound: path),*; $($async: ident)?, $($await: tt)*) => {
+		$(#[$meta])*
+		impl<T: $lockable $(+ $bound)*> $lockable for QuorumDriver<T> {
+			$($async)? fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+				let start = config.now();
+				let mut states = Vec::with_capacity(self.drivers.len());
+				for driver in &self.drivers {
+					states.push(driver.acquire_lock(config)$($await)*);
+				}
+
+				if let Some(state) = self.quorum_state(config, start, &states) {
+					return Ok(state);
+				}
+
+				// release the minority right away, without the at least hold, and on the drivers
+				// that failed too as their write may have been applied
+				for driver in &self.drivers {
+					let _ = driver.release_lock(config, &LockState::unlock())$($await)*;
+				}
+
+				// surface the error only if no driver answered
+				let answered = states.iter().any(Result::is_ok);
+				match states.into_iter().find_map(Result::err) {
+					Some(e) if !answered => Err(e),
+					_ => Ok(LockState::unlock()),
+				}
+			}
+
+			$($async)? fn release_lock(
+				&self,
+				config: &LockConfig,
+				state: &LockState,
+			) -> LockResult<LockState> {
+				let mut results = Vec::with_capacity(self.drivers.len());
+				for driver in &self.drivers {
+					let result = match driver.release_lock(config, state)$($await)* {
+						Err(LockError::NotOwner(_)) => Ok(LockState::unlock()),
+						result => result,
+					};
+					results.push(result);
+				}
+
+				self.quorum_release(results)
+			}
+
+			$($async)? fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+				let start = config.now();
+				let mut states = Vec::with_capacity(self.drivers.len());
+				for driver in &self.drivers {
+					states.push(driver.extend_lock(config)$($await)*);
+				}
+
+				if let Some(state) = self.quorum_state(config, start, &states) {
+					return Ok(state);
+				}
+
+				for driver in &self.drivers {
+					let _ = driver.release_lock(config, &LockState::unlock())$($await)*;
+				}
+				Ok(LockState::unlock())
+			}
+
+			$($async)? fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+				let mut results = Vec::with_capacity(self.drivers.len());
+				for driver in &self.drivers {
+					results.push(driver.leave_queue(config)$($await)*);
+				}
+				results.into_iter().collect()
+			}
+
+			$($async)? fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+				let mut results = Vec::with_capacity(self.drivers.len());
+				for driver in &self.drivers {
+					results.push(driver.inspect(config)$($await)*);
+				}
+
+				self.quorum_info(results)
+			}
+
+			fn watch_lost(&self, signal: &LostSignal) {
+				self.drivers.iter().for_each(|driver| driver.watch_lost(signal));
+			}
+
+			fn provider(&self) -> &'static str {
+				"quorum"
+			}
+		}
+	};
+}
+
+impl_lockable_quorum!(Lockable,;,);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_lockable_quorum!(#[async_trait::async_trait] AsyncLockable, Send, Sync; async, .await);
@@ -1,52 +1,541 @@
+use chrono::DateTime;
+use chrono::Duration;
 use chrono::Utc;
 
-use gethostname::gethostname;
-
-use redis::Value;
+use redis::Script;
+use std::sync::Arc;
 
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::admin::AsyncLockAdmin;
+use crate::admin::LockAdmin;
+use crate::clock::Clock;
+use crate::clock::SystemClock;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::core::AsyncLockable;
 use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockOwner;
 use crate::core::LockState;
 use crate::core::Lockable;
+use crate::error::LockError;
 use crate::error::LockResult;
+use crate::multilock::acquire_each;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::multilock::acquire_each_async;
+use crate::multilock::extend_each;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::multilock::extend_each_async;
+use crate::multilock::release_each;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::multilock::release_each_async;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::multilock::AsyncMultiLockable;
+use crate::multilock::MultiLockable;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::rwlock::AsyncRwLockable;
+use crate::rwlock::RwLockable;
+use crate::rwlock::RwMode;
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+use crate::semaphore::AsyncSemaphoreLockable;
+use crate::semaphore::SemaphoreLockable;
+use crate::telemetry::event;
+
+use super::help::from_millis;
 
 const KEY_PREFIX: &str = "dist_lock";
 
+/// Returns the new fencing token when acquired, otherwise 0.
+///
+/// KEYS[1]: lock key, KEYS[2]: fence key, ARGV[1]: value, ARGV[2]: max lock millis.
+const ACQUIRE_SCRIPT: &str = r"
+if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[2]) then
+	return redis.call('INCR', KEYS[2])
+end
+return 0
+";
+
+/// Returns 1 when released, 0 when the key is gone and -1 when held by another owner.
+///
+/// KEYS[1]: lock key, ARGV[1]: owner, ARGV[2]: remaining millis of the at least hold.
+const RELEASE_SCRIPT: &str = r"
+local value = redis.call('GET', KEYS[1])
+if not value then
+	return 0
+end
+if string.match(value, '^[^,]*,(.*)$') ~= ARGV[1] then
+	return -1
+end
+if tonumber(ARGV[2]) > 0 then
+	redis.call('PEXPIRE', KEYS[1], ARGV[2])
+else
+	redis.call('DEL', KEYS[1])
+end
+return 1
+";
+
+/// Returns the fencing token when extended, 0 when the key is gone and -1 when held by another
+/// owner.
+///
+/// KEYS[1]: lock key, KEYS[2]: fence key, ARGV[1]: owner, ARGV[2]: new value, ARGV[3]: max lock
+/// millis.
+const EXTEND_SCRIPT: &str = r"
+local value = redis.call('GET', KEYS[1])
+if not value then
+	return 0
+end
+if string.match(value, '^[^,]*,(.*)$') ~= ARGV[1] then
+	return -1
+end
+redis.call('SET', KEYS[1], ARGV[2], 'PX', ARGV[3])
+return tonumber(redis.call('GET', KEYS[2]) or 1)
+";
+
+/// Reentrant locks are stored as a hash of `owner`, `locked_at` and `holds`.
+///
+/// Returns the fencing token and the hold count, both 0 when held by another owner.
+///
+/// KEYS[1]: lock key, KEYS[2]: fence key, ARGV[1]: owner, ARGV[2]: locked at millis, ARGV[3]: max
+/// lock millis.
+const REENTRANT_ACQUIRE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+	redis.call('HSET', KEYS[1], 'owner', ARGV[1], 'locked_at', ARGV[2], 'holds', 1)
+	redis.call('PEXPIRE', KEYS[1], ARGV[3])
+	return {redis.call('INCR', KEYS[2]), 1}
+end
+if redis.call('TYPE', KEYS[1]).ok ~= 'hash' or redis.call('HGET', KEYS[1], 'owner') ~= ARGV[1] then
+	return {0, 0}
+end
+local holds = redis.call('HINCRBY', KEYS[1], 'holds', 1)
+redis.call('PEXPIRE', KEYS[1], ARGV[3])
+if holds == 1 then
+	redis.call('HSET', KEYS[1], 'locked_at', ARGV[2])
+	return {redis.call('INCR', KEYS[2]), 1}
+end
+return {tonumber(redis.call('GET', KEYS[2]) or 1), holds}
+";
+
+/// Returns the holds left, 0 when released or gone and -1 when held by another owner.
+///
+/// KEYS[1]: lock key, ARGV[1]: owner, ARGV[2]: remaining millis of the at least hold.
+const REENTRANT_RELEASE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+	return 0
+end
+if redis.call('TYPE', KEYS[1]).ok ~= 'hash' or redis.call('HGET', KEYS[1], 'owner') ~= ARGV[1] then
+	return -1
+end
+local holds = redis.call('HINCRBY', KEYS[1], 'holds', -1)
+if holds > 0 then
+	return holds
+end
+if tonumber(ARGV[2]) > 0 then
+	redis.call('HSET', KEYS[1], 'holds', 0)
+	redis.call('PEXPIRE', KEYS[1], ARGV[2])
+else
+	redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// Returns the fencing token and the hold count, {0, 0} when the key is gone and {-1, 0} when held
+/// by another owner.
+///
+/// KEYS[1]: lock key, KEYS[2]: fence key, ARGV[1]: owner, ARGV[2]: locked at millis, ARGV[3]: max
+/// lock millis.
+const REENTRANT_EXTEND_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+	return {0, 0}
+end
+if redis.call('TYPE', KEYS[1]).ok ~= 'hash' or redis.call('HGET', KEYS[1], 'owner') ~= ARGV[1] then
+	return {-1, 0}
+end
+redis.call('HSET', KEYS[1], 'locked_at', ARGV[2])
+redis.call('PEXPIRE', KEYS[1], ARGV[3])
+local holds = tonumber(redis.call('HGET', KEYS[1], 'holds'))
+return {tonumber(redis.call('GET', KEYS[2]) or 1), holds}
+";
+
+/// Read holds live in a sorted set of owners scored by their lease end, the write hold is a
+/// `timestamp,owner` string released and extended by `RELEASE_SCRIPT` and `EXTEND_SCRIPT`.
+///
+/// Returns 1 and the current fencing token when acquired, otherwise {0, 0}.
+///
+/// KEYS[1]: write key, KEYS[2]: readers key, KEYS[3]: fence key, ARGV[1]: owner, ARGV[2]: now
+/// millis, ARGV[3]: max lock millis.
+const READ_ACQUIRE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+	return {0, 0}
+end
+redis.call('ZREMRANGEBYSCORE', KEYS[2], '-inf', ARGV[2])
+redis.call('ZADD', KEYS[2], ARGV[2] + ARGV[3], ARGV[1])
+if redis.call('PTTL', KEYS[2]) < tonumber(ARGV[3]) then
+	redis.call('PEXPIRE', KEYS[2], ARGV[3])
+end
+return {1, tonumber(redis.call('GET', KEYS[3]) or 0)}
+";
+
+/// Returns 1 when released and 0 when the read hold is gone, also releases semaphore permits.
+///
+/// KEYS[1]: readers key, ARGV[1]: owner, ARGV[2]: now millis, ARGV[3]: remaining millis of the at
+/// least hold.
+const READ_RELEASE_SCRIPT: &str = r"
+local until = redis.call('ZSCORE', KEYS[1], ARGV[1])
+if not until or tonumber(until) <= tonumber(ARGV[2]) then
+	return 0
+end
+if tonumber(ARGV[3]) > 0 then
+	redis.call('ZADD', KEYS[1], ARGV[2] + ARGV[3], ARGV[1])
+else
+	redis.call('ZREM', KEYS[1], ARGV[1])
+end
+return 1
+";
+
+/// Returns 1 and the current fencing token when extended, {0, 0} when the read hold is gone, also
+/// extends semaphore permits.
+///
+/// KEYS[1]: readers key, KEYS[2]: fence key, ARGV[1]: owner, ARGV[2]: now millis, ARGV[3]: max
+/// lock millis.
+const READ_EXTEND_SCRIPT: &str = r"
+local until = redis.call('ZSCORE', KEYS[1], ARGV[1])
+if not until or tonumber(until) <= tonumber(ARGV[2]) then
+	return {0, 0}
+end
+redis.call('ZADD', KEYS[1], ARGV[2] + ARGV[3], ARGV[1])
+if redis.call('PTTL', KEYS[1]) < tonumber(ARGV[3]) then
+	redis.call('PEXPIRE', KEYS[1], ARGV[3])
+end
+return {1, tonumber(redis.call('GET', KEYS[2]) or 0)}
+";
+
+/// Returns the new fencing token when acquired, otherwise 0.
+///
+/// KEYS[1]: write key, KEYS[2]: readers key, KEYS[3]: fence key, ARGV[1]: value, ARGV[2]: now
+/// millis, ARGV[3]: max lock millis.
+const WRITE_ACQUIRE_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[1]) == 1 then
+	return 0
+end
+redis.call('ZREMRANGEBYSCORE', KEYS[2], '-inf', ARGV[2])
+if redis.call('ZCARD', KEYS[2]) > 0 then
+	return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[3])
+return redis.call('INCR', KEYS[3])
+";
+
+/// Semaphore permits live in a sorted set of owners scored by their lease end, expired permits are
+/// dropped before counting.
+///
+/// Returns the new fencing token when a permit is taken, otherwise 0.
+///
+/// KEYS[1]: permits key, KEYS[2]: fence key, ARGV[1]: owner, ARGV[2]: now millis, ARGV[3]: max
+/// lock millis, ARGV[4]: permits.
+const PERMIT_ACQUIRE_SCRIPT: &str = r"
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', ARGV[2])
+if not redis.call('ZSCORE', KEYS[1], ARGV[1]) and redis.call('ZCARD', KEYS[1]) >= tonumber(ARGV[4]) then
+	return 0
+end
+redis.call('ZADD', KEYS[1], ARGV[2] + ARGV[3], ARGV[1])
+if redis.call('PTTL', KEYS[1]) < tonumber(ARGV[3]) then
+	redis.call('PEXPIRE', KEYS[1], ARGV[3])
+end
+return redis.call('INCR', KEYS[2])
+";
+
+/// Locks every name of a `MultiLock` or none of them.
+///
+/// Returns the new fencing token of every lock when acquired, otherwise an empty array.
+///
+/// KEYS[1..n]: lock keys, KEYS[n+1..2n]: fence keys, ARGV[1]: value, ARGV[2]: max lock millis.
+const MULTI_ACQUIRE_SCRIPT: &str = r"
+local n = #KEYS / 2
+for i = 1, n do
+	if redis.call('EXISTS', KEYS[i]) == 1 then
+		return {}
+	end
+end
+local tokens = {}
+for i = 1, n do
+	redis.call('SET', KEYS[i], ARGV[1], 'PX', ARGV[2])
+	tokens[i] = redis.call('INCR', KEYS[n + i])
+end
+return tokens
+";
+
+/// Returns the number of released locks, locks held by another owner are left alone.
+///
+/// KEYS: lock keys, ARGV[1]: owner, ARGV[2..]: remaining millis of the at least hold of each lock.
+const MULTI_RELEASE_SCRIPT: &str = r"
+local released = 0
+for i, key in ipairs(KEYS) do
+	local value = redis.call('GET', key)
+	if value and string.match(value, '^[^,]*,(.*)$') == ARGV[1] then
+		if tonumber(ARGV[i + 1]) > 0 then
+			redis.call('PEXPIRE', key, ARGV[i + 1])
+		else
+			redis.call('DEL', key)
+		end
+		released = released + 1
+	end
+end
+return released
+";
+
+/// Returns the fencing token of every lock, 0 for a lock that is gone or held by another owner.
+///
+/// KEYS[1..n]: lock keys, KEYS[n+1..2n]: fence keys, ARGV[1]: owner, ARGV[2]: new value, ARGV[3]:
+/// max lock millis.
+const MULTI_EXTEND_SCRIPT: &str = r"
+local n = #KEYS / 2
+local tokens = {}
+for i = 1, n do
+	local value = redis.call('GET', KEYS[i])
+	if value and string.match(value, '^[^,]*,(.*)$') == ARGV[1] then
+		redis.call('SET', KEYS[i], ARGV[2], 'PX', ARGV[3])
+		tokens[i] = tonumber(redis.call('GET', KEYS[n + i]) or 1)
+	else
+		tokens[i] = 0
+	end
+end
+return tokens
+";
+
+/// Fair locks queue their waiters in a sorted set scored by arrival, their deadlines in a second
+/// sorted set. Waiters past their deadline are dropped, the lock is only taken by the head of the
+/// queue.
+///
+/// Returns the new fencing token when acquired, otherwise 0.
+///
+/// KEYS[1]: lock key, KEYS[2]: fence key, KEYS[3]: queue key, KEYS[4]: deadline key, ARGV[1]:
+/// value, ARGV[2]: max lock millis, ARGV[3]: owner, ARGV[4]: now millis, ARGV[5]: queue timeout
+/// millis.
+const FAIR_ACQUIRE_SCRIPT: &str = r"
+for _, waiter in ipairs(redis.call('ZRANGEBYSCORE', KEYS[4], '-inf', ARGV[4])) do
+	redis.call('ZREM', KEYS[3], waiter)
+	redis.call('ZREM', KEYS[4], waiter)
+end
+if not redis.call('ZSCORE', KEYS[3], ARGV[3]) then
+	local last = redis.call('ZRANGE', KEYS[3], -1, -1, 'WITHSCORES')
+	redis.call('ZADD', KEYS[3], (tonumber(last[2]) or 0) + 1, ARGV[3])
+end
+redis.call('ZADD', KEYS[4], ARGV[4] + ARGV[5], ARGV[3])
+redis.call('PEXPIRE', KEYS[3], ARGV[5])
+redis.call('PEXPIRE', KEYS[4], ARGV[5])
+if redis.call('ZRANGE', KEYS[3], 0, 0)[1] ~= ARGV[3] then
+	return 0
+end
+if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[2]) then
+	redis.call('ZREM', KEYS[3], ARGV[3])
+	redis.call('ZREM', KEYS[4], ARGV[3])
+	return redis.call('INCR', KEYS[2])
+end
+return 0
+";
+
+/// Owner, locked at millis, remaining millis, fencing token and hold count of a plain or
+/// reentrant lock, nil when the lock is free.
+///
+/// KEYS[1]: lock key, KEYS[2]: fence key.
+const INSPECT_SCRIPT: &str = r"
+local ttl = redis.call('PTTL', KEYS[1])
+if ttl < 0 then
+	return false
+end
+local token = tonumber(redis.call('GET', KEYS[2]) or 0)
+if redis.call('TYPE', KEYS[1]).ok == 'hash' then
+	local lock = redis.call('HMGET', KEYS[1], 'owner', 'locked_at', 'holds')
+	return {lock[1], tonumber(lock[2]), ttl, token, tonumber(lock[3])}
+end
+local value = redis.call('GET', KEYS[1])
+local sep = string.find(value, ',', 1, true)
+return {string.sub(value, sep + 1), tonumber(string.sub(value, 1, sep - 1)), ttl, token, 1}
+";
+
+/// Returns 1 when a lock was deleted, otherwise 0. The fence key is kept so that fencing tokens
+/// keep increasing.
+///
+/// KEYS[1]: lock key, KEYS[2]: write key, KEYS[3]: readers key, KEYS[4]: permits key, KEYS[5]:
+/// queue key, KEYS[6]: deadline key.
+const FORCE_RELEASE_SCRIPT: &str = r"
+local held = redis.call('DEL', KEYS[1], KEYS[2], KEYS[3], KEYS[4])
+redis.call('DEL', KEYS[5], KEYS[6])
+return math.min(held, 1)
+";
+
+/// Seconds and microseconds of the Redis clock.
+const TIME_SCRIPT: &str = "return redis.call('TIME')";
+
+/// KEYS[1]: queue key, KEYS[2]: deadline key, ARGV[1]: owner.
+const LEAVE_QUEUE_SCRIPT: &str = r"
+redis.call('ZREM', KEYS[1], ARGV[1])
+return redis.call('ZREM', KEYS[2], ARGV[1])
+";
+
+const NOT_OWNER: i64 = -1;
+
+/// Stores the lock in `dist_lock:{name}` and its fencing counter in `dist_lock:{name}:fence`, the
+/// hash tag keeps both keys in one cluster slot. A plain lock is a `timestamp,owner` string, a
+/// reentrant lock a hash that counts the holds of its owner.
+///
+/// Older releases used `dist_lock:name`, they don't exclude clients of this one.
+///
+/// Lock calls read the time from the clock of their `LockConfig`, `LockAdmin` calls from the clock
+/// of the driver, `SystemClock` unless set with `with_clock`. Cluster clients only support
+/// `force_unlock`, `SCAN` would only walk the node it is sent to.
 #[derive(Debug)]
 pub struct RedisDriver<'a, T> {
 	key: String,
+	fence_key: String,
 	transport: &'a T,
+	clock: Arc<dyn Clock>,
 }
 
 impl<'a, T> RedisDriver<'a, T> {
-	pub fn new(lock_name: &String, transport: &'a T) -> Self {
-		RedisDriver { key: format!("{}:{}", KEY_PREFIX, lock_name), transport }
+	pub fn new(lock_name: &str, transport: &'a T) -> Self {
+		let (key, fence_key) = lock_keys(lock_name);
+		RedisDriver { key, fence_key, transport, clock: Arc::new(SystemClock) }
+	}
+
+	/// Clock of the `LockAdmin` calls, should be the clock of the configs locking through this
+	/// driver.
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Keys of a fair lock: `dist_lock:{name}:queue` and `dist_lock:{name}:deadlines`.
+	fn queue_keys(&self) -> (String, String) {
+		(format!("{}:queue", self.key), format!("{}:deadlines", self.key))
+	}
+
+	/// Key of a `DistSemaphore`: `dist_lock:{name}:permits`.
+	fn permits_key(&self) -> String {
+		format!("{}:permits", self.key)
+	}
+
+	/// Keys of a `DistRwLock`: `dist_lock:{name}:write` and `dist_lock:{name}:readers`.
+	fn rw_keys(&self) -> (String, String) {
+		(format!("{}:write", self.key), format!("{}:readers", self.key))
+	}
+
+	/// Keys `force_unlock` deletes: every key of the lock but the fence key, see
+	/// `FORCE_RELEASE_SCRIPT`.
+	fn unlock_keys(&self) -> [String; 6] {
+		let (write_key, readers_key) = self.rw_keys();
+		let (queue_key, deadline_key) = self.queue_keys();
+		[self.key.clone(), write_key, readers_key, self.permits_key(), queue_key, deadline_key]
 	}
 
 	#[inline(always)]
-	fn build_value() -> String {
-		format!("{},{}", Utc::now().timestamp_millis(), gethostname().to_string_lossy())
+	fn build_value(config: &LockConfig) -> String {
+		format!("{},{}", config.now().timestamp_millis(), config.owner())
 	}
 }
 
+/// Lock key and fence key of `lock_name`.
+fn lock_keys(lock_name: &str) -> (String, String) {
+	let key = format!("{}:{{{}}}", KEY_PREFIX, lock_name);
+	(key.clone(), format!("{}:fence", key))
+}
+
+/// Lock name of a lock key, `None` for the other keys of the driver.
+fn lock_name(key: &str) -> Option<&str> {
+	key.strip_prefix(KEY_PREFIX)?.strip_prefix(":{")?.strip_suffix('}')
+}
+
+/// Reply of `INSPECT_SCRIPT`.
+type InspectReply = Option<(String, i64, i64, i64, u32)>;
+
+fn lock_info(
+	lock_name: &str,
+	reply: InspectReply,
+	now: DateTime<Utc>,
+) -> LockResult<Option<LockInfo>> {
+	let Some((owner, locked_at, ttl, token, holds)) = reply else {
+		return Ok(None);
+	};
+
+	let lock_until = now + Duration::milliseconds(ttl);
+	let owner = LockOwner::from_id(owner);
+	let info = LockInfo::new(lock_name, owner, from_millis(locked_at)?, lock_until);
+	Ok(Some(info.with_token(token).with_holds(holds)))
+}
+
+/// Lock keys followed by fence keys of every config of a `MultiLock`, the lock name of the driver
+/// is not used.
+fn multi_lock_keys(configs: &[LockConfig]) -> Vec<String> {
+	let (keys, fence_keys): (Vec<_>, Vec<_>) =
+		configs.iter().map(|config| lock_keys(&config.name)).unzip();
+	keys.into_iter().chain(fence_keys).collect()
+}
+
+/// Millis of the Redis clock if the config uses backend time, otherwise of the local clock.
+macro_rules! now_millis {
+	($config: expr, $conn: expr, $invoke_fn_name: ident $(, $await: tt)*) => {
+		match $config.backend_time {
+			true => {
+				let (secs, micros): (i64, i64) =
+					Script::new(TIME_SCRIPT).$invoke_fn_name($conn)$($await)*?;
+				event!(secs, micros, "read the redis server time");
+				secs * 1000 + micros / 1000
+			}
+			false => $config.now().timestamp_millis(),
+		}
+	};
+}
+
 macro_rules! impl_lockable_redis {
-	($client:ty,
+	(
+		$(#[$meta: meta])*
+		$lockable: ident,
+		$client:ty,
 		$conn_fn_name: ident,
-		$query_fn_name: ident,
+		$invoke_fn_name: ident,
 		$($async: ident)?,
 		$($await: tt)*
 	) => {
-		#[cfg_attr(any(feature = "tokio", feature = "async-std"), async_trait::async_trait)]
-		impl<'a> Lockable for RedisDriver<'a, $client> {
+		$(#[$meta])*
+		impl<'a> $lockable for RedisDriver<'a, $client> {
 			$($async)? fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
 				let mut conn = self.transport.$conn_fn_name()$($await)*?;
-				let value: Value = redis::cmd("SET")
-					.arg(&self.key)
-					.arg(Self::build_value())
-					.arg("NX")
-					.arg("PX")
+				if config.reentrant {
+					let now = now_millis!(config, &mut conn, $invoke_fn_name $(, $await)*);
+					let (token, holds): (i64, u32) = Script::new(REENTRANT_ACQUIRE_SCRIPT)
+						.key(&self.key)
+						.key(&self.fence_key)
+						.arg(config.owner().id())
+						.arg(now)
+						.arg(config.max_lock.num_milliseconds() as usize)
+						.$invoke_fn_name(&mut conn)$($await)*?;
+					let state = LockState::new(token > 0, config.now()).with_token(token);
+					return Ok(state.with_holds(holds));
+				}
+
+				if let Some(queue_timeout) = config.queue_timeout {
+					let now = now_millis!(config, &mut conn, $invoke_fn_name $(, $await)*);
+					let (queue_key, deadline_key) = self.queue_keys();
+					let token: i64 = Script::new(FAIR_ACQUIRE_SCRIPT)
+						.key(&self.key)
+						.key(&self.fence_key)
+						.key(queue_key)
+						.key(deadline_key)
+						.arg(Self::build_value(config))
+						.arg(config.max_lock.num_milliseconds() as usize)
+						.arg(config.owner().id())
+						.arg(now)
+						.arg(queue_timeout.num_milliseconds())
+						.$invoke_fn_name(&mut conn)$($await)*?;
+					return Ok(LockState::new(token > 0, config.now()).with_token(token));
+				}
+
+				let token: i64 = Script::new(ACQUIRE_SCRIPT)
+					.key(&self.key)
+					.key(&self.fence_key)
+					.arg(Self::build_value(config))
 					.arg(config.max_lock.num_milliseconds() as usize)
-					.$query_fn_name(&mut conn)$($await)*?;
-				Ok(LockState::new(matches!(value, Value::Okay), Utc::now()))
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				Ok(LockState::new(token > 0, config.now()).with_token(token))
 			}
 
 			$($async)? fn release_lock(
@@ -54,50 +543,629 @@ macro_rules! impl_lockable_redis {
 				config: &LockConfig,
 				state: &LockState,
 			) -> LockResult<LockState> {
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let remaining = config.at_least_remaining(state.locked_at).num_milliseconds();
+				let script = match config.reentrant {
+					true => REENTRANT_RELEASE_SCRIPT,
+					false => RELEASE_SCRIPT,
+				};
+				let result: i64 = Script::new(script)
+					.key(&self.key)
+					.arg(config.owner().id())
+					.arg(remaining)
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				match result {
+					NOT_OWNER => Err(LockError::NotOwner(config.name.clone())),
+					holds if config.reentrant && holds > 0 => Ok(state.with_holds(holds as u32)),
+					_ => Ok(LockState::unlock()),
+				}
+			}
+
+			$($async)? fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				if config.reentrant {
+					let now = now_millis!(config, &mut conn, $invoke_fn_name $(, $await)*);
+					let (token, holds): (i64, u32) = Script::new(REENTRANT_EXTEND_SCRIPT)
+						.key(&self.key)
+						.key(&self.fence_key)
+						.arg(config.owner().id())
+						.arg(now)
+						.arg(config.max_lock.num_milliseconds() as usize)
+						.$invoke_fn_name(&mut conn)$($await)*?;
+					return match token {
+						NOT_OWNER => Err(LockError::NotOwner(config.name.clone())),
+						_ => Ok(LockState::new(token > 0, config.now()).with_token(token).with_holds(holds)),
+					};
+				}
+
+				let result: i64 = Script::new(EXTEND_SCRIPT)
+					.key(&self.key)
+					.key(&self.fence_key)
+					.arg(config.owner().id())
+					.arg(Self::build_value(config))
+					.arg(config.max_lock.num_milliseconds() as usize)
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				match result {
+					NOT_OWNER => Err(LockError::NotOwner(config.name.clone())),
+					_ => Ok(LockState::new(result > 0, config.now()).with_token(result)),
+				}
+			}
+
+			$($async)? fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+				let (queue_key, deadline_key) = self.queue_keys();
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let _: i64 = Script::new(LEAVE_QUEUE_SCRIPT)
+					.key(queue_key)
+					.key(deadline_key)
+					.arg(config.owner().id())
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				Ok(())
+			}
+
+			$($async)? fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let reply = Script::new(INSPECT_SCRIPT)
+					.key(&self.key)
+					.key(&self.fence_key)
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				lock_info(&config.name, reply, config.now())
+			}
+
+			fn provider(&self) -> &'static str {
+				"redis"
+			}
+		}
+	}
+}
+
+impl_lockable_redis!(Lockable, ::redis::Client, get_connection, invoke,,);
+impl_lockable_redis!(Lockable, ::redis::cluster::ClusterClient, get_connection, invoke,,);
+
+#[cfg(feature = "r2d2")]
+impl_lockable_redis!(Lockable, ::r2d2::Pool<::redis::cluster::ClusterClient>, get, invoke,,);
+#[cfg(feature = "r2d2")]
+impl_lockable_redis!(Lockable, ::r2d2::Pool<::redis::Client>, get, invoke,,);
+
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncLockable,
+	::redis::Client,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncLockable,
+	::redis::cluster::ClusterClient,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+
+macro_rules! impl_lock_admin_redis {
+	(
+		$(#[$meta: meta])*
+		$admin: ident,
+		$client:ty,
+		$conn_fn_name: ident,
+		$invoke_fn_name: ident,
+		$query_fn_name: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		impl<'a> $admin for RedisDriver<'a, $client> {
+			$($async)? fn list_locks(&self) -> LockResult<Vec<LockInfo>> {
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let pattern = format!("{}:{{*}}", KEY_PREFIX);
+				let mut keys = Vec::new();
+				let mut cursor = 0;
+				loop {
+					let (next, batch): (u64, Vec<String>) = ::redis::cmd("SCAN")
+						.cursor_arg(cursor)
+						.arg("MATCH")
+						.arg(&pattern)
+						.$query_fn_name(&mut conn)$($await)*?;
+					keys.extend(batch);
+					if next == 0 {
+						break;
+					}
+					cursor = next;
+				}
+
+				let mut locks = Vec::with_capacity(keys.len());
+				for key in keys {
+					let Some(name) = lock_name(&key) else {
+						continue;
+					};
+
+					let (key, fence_key) = lock_keys(name);
+					let reply = Script::new(INSPECT_SCRIPT)
+						.key(key)
+						.key(fence_key)
+						.$invoke_fn_name(&mut conn)$($await)*?;
+					locks.extend(lock_info(name, reply, self.clock.now())?);
+				}
+				Ok(locks)
+			}
+
+			$($async)? fn force_unlock(&self, name: &str) -> LockResult<bool> {
+				force_unlock!(self, name, $conn_fn_name, $invoke_fn_name $(, $await)*)
+			}
+		}
+	};
+}
+
+/// Delete every key of the lock on `name` but its fence key, `true` if it was held. The keys share
+/// the hash tag of the name, so this works on a cluster too.
+macro_rules! force_unlock {
+	($self: expr, $name: expr, $conn_fn_name: ident, $invoke_fn_name: ident $(, $await: tt)*) => {{
+		let keys = RedisDriver::new($name, $self.transport).unlock_keys();
+		let mut conn = $self.transport.$conn_fn_name()$($await)*?;
+		let deleted: i64 =
+			Script::new(FORCE_RELEASE_SCRIPT).key(&keys[..]).$invoke_fn_name(&mut conn)$($await)*?;
+		Ok(deleted > 0)
+	}};
+}
+
+macro_rules! impl_lock_admin_cluster {
+	(
+		$(#[$meta: meta])*
+		$admin: ident,
+		$client:ty,
+		$conn_fn_name: ident,
+		$invoke_fn_name: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		impl<'a> $admin for RedisDriver<'a, $client> {
+			$($async)? fn list_locks(&self) -> LockResult<Vec<LockInfo>> {
+				Err(LockError::InvalidLock("list_locks is unsupported on a redis cluster".to_string()))
+			}
+
+			$($async)? fn force_unlock(&self, name: &str) -> LockResult<bool> {
+				force_unlock!(self, name, $conn_fn_name, $invoke_fn_name $(, $await)*)
+			}
+		}
+	};
+}
+
+impl_lock_admin_redis!(LockAdmin, ::redis::Client, get_connection, invoke, query,,);
+impl_lock_admin_cluster!(LockAdmin, ::redis::cluster::ClusterClient, get_connection, invoke,,);
+
+#[cfg(feature = "r2d2")]
+impl_lock_admin_redis!(LockAdmin, ::r2d2::Pool<::redis::Client>, get, invoke, query,,);
+#[cfg(feature = "r2d2")]
+impl_lock_admin_cluster!(LockAdmin, ::r2d2::Pool<::redis::cluster::ClusterClient>, get, invoke,,);
+
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_lock_admin_redis!(
+	#[async_trait::async_trait]
+	AsyncLockAdmin,
+	::redis::Client,
+	get_async_connection,
+	invoke_async,
+	query_async,
+	async,
+	.await
+);
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_lock_admin_cluster!(
+	#[async_trait::async_trait]
+	AsyncLockAdmin,
+	::redis::cluster::ClusterClient,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+
+macro_rules! impl_rw_lockable_redis {
+	(
+		$(#[$meta: meta])*
+		$lockable: ident,
+		$client:ty,
+		$conn_fn_name: ident,
+		$invoke_fn_name: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		impl<'a> $lockable for RedisDriver<'a, $client> {
+			$($async)? fn acquire_rw_lock(
+				&self,
+				config: &LockConfig,
+				mode: RwMode,
+			) -> LockResult<LockState> {
+				let (write_key, readers_key) = self.rw_keys();
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let now = config.now();
+				let (locked, token): (i64, i64) = match mode {
+					RwMode::Read => Script::new(READ_ACQUIRE_SCRIPT)
+						.key(&write_key)
+						.key(&readers_key)
+						.key(&self.fence_key)
+						.arg(config.owner().id())
+						.arg(now.timestamp_millis())
+						.arg(config.max_lock.num_milliseconds())
+						.$invoke_fn_name(&mut conn)$($await)*?,
+					RwMode::Write => {
+						let token: i64 = Script::new(WRITE_ACQUIRE_SCRIPT)
+							.key(&write_key)
+							.key(&readers_key)
+							.key(&self.fence_key)
+							.arg(Self::build_value(config))
+							.arg(now.timestamp_millis())
+							.arg(config.max_lock.num_milliseconds())
+							.$invoke_fn_name(&mut conn)$($await)*?;
+						(token, token)
+					}
+				};
+				Ok(LockState::new(locked > 0, now).with_token(token))
+			}
+
+			$($async)? fn release_rw_lock(
+				&self,
+				config: &LockConfig,
+				mode: RwMode,
+				state: &LockState,
+			) -> LockResult<LockState> {
+				let (write_key, readers_key) = self.rw_keys();
 				let until = config.lock_at_least_until(state.locked_at);
 				let mut conn = self.transport.$conn_fn_name()$($await)*?;
-				let remaining = (until - Utc::now()).num_milliseconds();
-				if remaining > 0 {
-					redis::cmd("SET")
-						.arg(&self.key)
-						.arg(Self::build_value())
-						.arg("XX")
-						.arg("PX")
+				let now = config.now();
+				let remaining = (until - now).num_milliseconds().max(0);
+				let result: i64 = match mode {
+					RwMode::Read => Script::new(READ_RELEASE_SCRIPT)
+						.key(&readers_key)
+						.arg(config.owner().id())
+						.arg(now.timestamp_millis())
 						.arg(remaining)
-						.$query_fn_name(&mut conn)$($await)*?;
-				} else {
-					redis::cmd("DEL").arg(&self.key).$query_fn_name(&mut conn)$($await)*?;
+						.$invoke_fn_name(&mut conn)$($await)*?,
+					RwMode::Write => Script::new(RELEASE_SCRIPT)
+						.key(&write_key)
+						.arg(config.owner().id())
+						.arg(remaining)
+						.$invoke_fn_name(&mut conn)$($await)*?,
+				};
+				if result == NOT_OWNER {
+					return Err(LockError::NotOwner(config.name.clone()));
 				}
 
 				Ok(LockState::unlock())
 			}
 
-			$($async)? fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+			$($async)? fn extend_rw_lock(
+				&self,
+				config: &LockConfig,
+				mode: RwMode,
+			) -> LockResult<LockState> {
+				let (write_key, readers_key) = self.rw_keys();
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let now = config.now();
+				let (locked, token): (i64, i64) = match mode {
+					RwMode::Read => Script::new(READ_EXTEND_SCRIPT)
+						.key(&readers_key)
+						.key(&self.fence_key)
+						.arg(config.owner().id())
+						.arg(now.timestamp_millis())
+						.arg(config.max_lock.num_milliseconds())
+						.$invoke_fn_name(&mut conn)$($await)*?,
+					RwMode::Write => {
+						let token: i64 = Script::new(EXTEND_SCRIPT)
+							.key(&write_key)
+							.key(&self.fence_key)
+							.arg(config.owner().id())
+							.arg(Self::build_value(config))
+							.arg(config.max_lock.num_milliseconds())
+							.$invoke_fn_name(&mut conn)$($await)*?;
+						(token, token)
+					}
+				};
+				match locked {
+					NOT_OWNER => Err(LockError::NotOwner(config.name.clone())),
+					_ => Ok(LockState::new(locked > 0, now).with_token(token)),
+				}
+			}
+		}
+	}
+}
+
+impl_rw_lockable_redis!(RwLockable, ::redis::Client, get_connection, invoke,,);
+impl_rw_lockable_redis!(RwLockable, ::redis::cluster::ClusterClient, get_connection, invoke,,);
+
+#[cfg(feature = "r2d2")]
+impl_rw_lockable_redis!(RwLockable, ::r2d2::Pool<::redis::cluster::ClusterClient>, get, invoke,,);
+#[cfg(feature = "r2d2")]
+impl_rw_lockable_redis!(RwLockable, ::r2d2::Pool<::redis::Client>, get, invoke,,);
+
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_rw_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncRwLockable,
+	::redis::Client,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_rw_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncRwLockable,
+	::redis::cluster::ClusterClient,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+
+macro_rules! impl_semaphore_lockable_redis {
+	(
+		$(#[$meta: meta])*
+		$lockable: ident,
+		$client:ty,
+		$conn_fn_name: ident,
+		$invoke_fn_name: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		impl<'a> $lockable for RedisDriver<'a, $client> {
+			$($async)? fn acquire_permit(
+				&self,
+				config: &LockConfig,
+				permits: u32,
+			) -> LockResult<LockState> {
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let now = config.now();
+				let token: i64 = Script::new(PERMIT_ACQUIRE_SCRIPT)
+					.key(self.permits_key())
+					.key(&self.fence_key)
+					.arg(config.owner().id())
+					.arg(now.timestamp_millis())
+					.arg(config.max_lock.num_milliseconds())
+					.arg(permits)
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				Ok(LockState::new(token > 0, now).with_token(token))
+			}
+
+			$($async)? fn release_permit(
+				&self,
+				config: &LockConfig,
+				state: &LockState,
+			) -> LockResult<LockState> {
+				let until = config.lock_at_least_until(state.locked_at);
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let now = config.now();
+				let _: i64 = Script::new(READ_RELEASE_SCRIPT)
+					.key(self.permits_key())
+					.arg(config.owner().id())
+					.arg(now.timestamp_millis())
+					.arg((until - now).num_milliseconds().max(0))
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				Ok(LockState::unlock())
+			}
+
+			$($async)? fn extend_permit(
+				&self,
+				config: &LockConfig,
+				state: &LockState,
+			) -> LockResult<LockState> {
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let now = config.now();
+				let (extended, _): (i64, i64) = Script::new(READ_EXTEND_SCRIPT)
+					.key(self.permits_key())
+					.key(&self.fence_key)
+					.arg(config.owner().id())
+					.arg(now.timestamp_millis())
+					.arg(config.max_lock.num_milliseconds())
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				if extended == 0 {
+					return Ok(LockState::unlock());
+				}
+
+				Ok(LockState::new(true, now).with_token(state.token))
+			}
+		}
+	}
+}
+
+impl_semaphore_lockable_redis!(SemaphoreLockable, ::redis::Client, get_connection, invoke,,);
+impl_semaphore_lockable_redis!(
+	SemaphoreLockable,
+	::redis::cluster::ClusterClient,
+	get_connection,
+	invoke,,
+);
+
+#[cfg(feature = "r2d2")]
+impl_semaphore_lockable_redis!(
+	SemaphoreLockable,
+	::r2d2::Pool<::redis::cluster::ClusterClient>,
+	get,
+	invoke,,
+);
+#[cfg(feature = "r2d2")]
+impl_semaphore_lockable_redis!(SemaphoreLockable, ::r2d2::Pool<::redis::Client>, get, invoke,,);
+
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_semaphore_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncSemaphoreLockable,
+	::redis::Client,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_semaphore_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncSemaphoreLockable,
+	::redis::cluster::ClusterClient,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+
+macro_rules! impl_multi_lockable_redis {
+	(
+		$(#[$meta: meta])*
+		$lockable: ident,
+		$client:ty,
+		$conn_fn_name: ident,
+		$invoke_fn_name: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		impl<'a> $lockable for RedisDriver<'a, $client> {
+			$($async)? fn acquire_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+				let Some(config) = configs.first() else {
+					return Ok(vec![]);
+				};
+
 				let mut conn = self.transport.$conn_fn_name()$($await)*?;
-				let value: Value = redis::cmd("SET")
-					.arg(&self.key)
-					.arg(Self::build_value())
-					.arg("XX")
-					.arg("PX")
+				let tokens: Vec<i64> = Script::new(MULTI_ACQUIRE_SCRIPT)
+					.key(multi_lock_keys(configs))
+					.arg(Self::build_value(config))
 					.arg(config.max_lock.num_milliseconds() as usize)
-					.$query_fn_name(&mut conn)$($await)*?;
-				Ok(LockState::new(matches!(value, Value::Okay), Utc::now()))
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				if tokens.len() != configs.len() {
+					return Ok(vec![LockState::unlock(); configs.len()]);
+				}
+
+				let now = config.now();
+				Ok(tokens.into_iter().map(|token| LockState::new(true, now).with_token(token)).collect())
+			}
+
+			$($async)? fn release_locks(
+				&self,
+				configs: &[LockConfig],
+				states: &[LockState],
+			) -> LockResult<Vec<LockState>> {
+				let Some(config) = configs.first() else {
+					return Ok(vec![]);
+				};
+
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let keys = configs.iter().map(|config| lock_keys(&config.name).0).collect::<Vec<_>>();
+				let remaining = configs
+					.iter()
+					.zip(states)
+					.map(|(config, state)| {
+						let until = config.lock_at_least_until(state.locked_at);
+						(until - config.now()).num_milliseconds().max(0)
+					})
+					.collect::<Vec<_>>();
+				let _: i64 = Script::new(MULTI_RELEASE_SCRIPT)
+					.key(keys)
+					.arg(config.owner().id())
+					.arg(remaining)
+					.$invoke_fn_name(&mut conn)$($await)*?;
+				Ok(vec![LockState::unlock(); configs.len()])
+			}
+
+			$($async)? fn extend_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+				let Some(config) = configs.first() else {
+					return Ok(vec![]);
+				};
+
+				let mut conn = self.transport.$conn_fn_name()$($await)*?;
+				let tokens: Vec<i64> = Script::new(MULTI_EXTEND_SCRIPT)
+					.key(multi_lock_keys(configs))
+					.arg(config.owner().id())
+					.arg(Self::build_value(config))
+					.arg(config.max_lock.num_milliseconds() as usize)
+					.$invoke_fn_name(&mut conn)$($await)*?;
+
+				let now = config.now();
+				Ok(tokens.into_iter().map(|token| LockState::new(token > 0, now).with_token(token)).collect())
 			}
 		}
 	}
 }
 
-#[cfg(any(feature = "tokio", feature = "async-std"))]
-impl_lockable_redis!(::redis::cluster::ClusterClient, get_async_connection, query_async, async, .await);
-#[cfg(any(feature = "tokio", feature = "async-std"))]
-impl_lockable_redis!(::redis::Client, get_async_connection, query_async, async, .await);
+/// The names of a multi-lock fall in different hash slots, which a cluster can't script over, so
+/// cluster clients lock them one by one.
+macro_rules! impl_multi_lockable_cluster {
+	(
+		$(#[$meta: meta])*
+		$lockable: ident,
+		$client:ty,
+		$acquire: ident,
+		$release: ident,
+		$extend: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		impl<'a> $lockable for RedisDriver<'a, $client> {
+			$($async)? fn acquire_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+				$acquire(self, configs)$($await)*
+			}
 
-#[cfg(not(any(feature = "tokio", feature = "async-std")))]
-impl_lockable_redis!(::redis::Client, get_connection, query,,);
-#[cfg(not(any(feature = "tokio", feature = "async-std")))]
-impl_lockable_redis!(::redis::cluster::ClusterClient, get_connection, query,,);
+			$($async)? fn release_locks(
+				&self,
+				configs: &[LockConfig],
+				states: &[LockState],
+			) -> LockResult<Vec<LockState>> {
+				$release(self, configs, states)$($await)*
+			}
+
+			$($async)? fn extend_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+				$extend(self, configs)$($await)*
+			}
+		}
+	}
+}
+
+impl_multi_lockable_redis!(MultiLockable, ::redis::Client, get_connection, invoke,,);
+impl_multi_lockable_cluster!(
+	MultiLockable,
+	::redis::cluster::ClusterClient,
+	acquire_each,
+	release_each,
+	extend_each,,
+);
 
 #[cfg(feature = "r2d2")]
-impl_lockable_redis!(::r2d2::Pool<::redis::cluster::ClusterClient> , get, query,,);
+impl_multi_lockable_cluster!(
+	MultiLockable,
+	::r2d2::Pool<::redis::cluster::ClusterClient>,
+	acquire_each,
+	release_each,
+	extend_each,,
+);
 #[cfg(feature = "r2d2")]
-impl_lockable_redis!(::r2d2::Pool<::redis::Client> , get, query,,);
+impl_multi_lockable_redis!(MultiLockable, ::r2d2::Pool<::redis::Client>, get, invoke,,);
+
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_multi_lockable_redis!(
+	#[async_trait::async_trait]
+	AsyncMultiLockable,
+	::redis::Client,
+	get_async_connection,
+	invoke_async,
+	async,
+	.await
+);
+#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+impl_multi_lockable_cluster!(
+	#[async_trait::async_trait]
+	AsyncMultiLockable,
+	::redis::cluster::ClusterClient,
+	acquire_each_async,
+	release_each_async,
+	extend_each_async,
+	async,
+	.await
+);
@@ -1,20 +1,48 @@
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
 use chrono::DateTime;
+use chrono::Duration;
 use chrono::Utc;
 use zookeeper::Acl;
 use zookeeper::CreateMode;
+use zookeeper::Stat;
+use zookeeper::Subscription;
+use zookeeper::ZkError;
+use zookeeper::ZkState;
 use zookeeper::ZooKeeper;
 
+use crate::admin::LockAdmin;
 use crate::core::LockConfig;
+use crate::core::LockInfo;
+use crate::core::LockOwner;
 use crate::core::LockState;
 use crate::core::Lockable;
 use crate::error::LockError;
 use crate::error::LockResult;
+use crate::multilock::acquire_each;
+use crate::multilock::extend_each;
+use crate::multilock::release_each;
+use crate::multilock::MultiLockable;
+use crate::rwlock::RwLockable;
+use crate::rwlock::RwMode;
+use crate::semaphore::SemaphoreLockable;
+use crate::signal::LostReason;
+use crate::signal::LostSignal;
+use crate::telemetry::event;
+
+use super::help::from_millis;
 
 const DEFAULT_PARENT_PATH: &str = "/dist_lock";
+const PERMIT_PREFIX: &str = "permit";
+const WAITER_PREFIX: &str = "waiter";
+/// Length of the node data written before the owner and hold count were stored.
+const LEGACY_NODE_LEN: usize = 8;
 
 pub struct ZookeeperDriver<'a> {
 	parent: String,
 	transport: &'a ZooKeeper,
+	subscription: Mutex<Option<Subscription>>,
 }
 
 impl ZookeeperDriver<'_> {
@@ -34,35 +62,151 @@ impl ZookeeperDriver<'_> {
 			None => DEFAULT_PARENT_PATH.to_owned(),
 		};
 
-		Ok(ZookeeperDriver { parent: formatted, transport })
+		Ok(ZookeeperDriver { parent: formatted, transport, subscription: Mutex::new(None) })
 	}
 
 	pub fn path(&self, name: &str) -> String {
 		format!("{}/{}", &self.parent, &name)
 	}
 
+	/// Parent of the `waiter-` nodes of a fair lock, a sibling of the lock node so that the lock
+	/// node can still be deleted while others wait.
+	pub fn queue_path(&self, name: &str) -> String {
+		format!("{}.queue", self.path(name))
+	}
+
 	pub fn transport(&self) -> &ZooKeeper {
 		self.transport
 	}
 
 	pub fn check_locked(&self, path: &str, config: &LockConfig) -> LockResult<bool> {
-		if self.transport.exists(path, false)?.is_some() {
-			let tuple = self.transport.get_data(path, false)?;
+		match self.read_node(path, Some(config.max_lock))? {
+			Some((Some(node), _)) => Ok(node.lock_until > self.now(config)?),
+			_ => Ok(false),
+		}
+	}
 
-			let ts = i64::from_be_bytes(tuple.0.try_into().map_err(|_| {
-				LockError::InvalidLock("can't parse zk data to timestamp".to_string())
-			})?);
-			let lock_time =
-				DateTime::from_timestamp(ts / 1000, ((ts % 1000) * 1_000_000) as u32).ok_or(
-					LockError::InvalidLock(format!("convert ts: {} to DateTime failed", ts)),
-				)?;
+	/// Time of the ensemble if `config` uses backend time, the `mtime` of the `{parent}.clock`
+	/// node written for the purpose. Otherwise the time of this host.
+	fn now(&self, config: &LockConfig) -> LockResult<DateTime<Utc>> {
+		if !config.backend_time {
+			return Ok(config.now());
+		}
 
-			Ok(lock_time + config.max_lock > Utc::now())
-		} else {
-			Ok(false)
+		let path = format!("{}.clock", self.parent);
+		let stat = match self.transport.set_data(&path, vec![], None) {
+			Err(ZkError::NoNode) => {
+				self.create_zk_path(&path)?;
+				self.transport.set_data(&path, vec![], None)?
+			}
+			result => result?,
+		};
+		from_millis(stat.mtime)
+	}
+
+	/// Node at `path`, a legacy node is held for `max_lock` or indefinitely if there is none.
+	fn read_node(
+		&self,
+		path: &str,
+		max_lock: Option<Duration>,
+	) -> LockResult<Option<(Option<NodeData>, Stat)>> {
+		match self.transport.get_data(path, false) {
+			Ok((data, stat)) => Ok(Some((NodeData::decode(&data, max_lock)?, stat))),
+			Err(ZkError::NoNode) => Ok(None),
+			Err(e) => Err(e.into()),
 		}
 	}
 
+	/// Delete `path` unless it is already gone or was changed since `version`.
+	fn delete_node(&self, path: &str, version: Option<i32>) -> LockResult<()> {
+		match self.transport.delete(path, version) {
+			Ok(_) | Err(ZkError::NoNode) | Err(ZkError::BadVersion) => Ok(()),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Latest sequential node named `{prefix}-` of `owner` under `dir`.
+	fn find_node(
+		&self,
+		dir: &str,
+		prefix: &str,
+		owner: &LockOwner,
+	) -> LockResult<Option<(String, NodeData, Stat)>> {
+		let prefix = format!("{}-", prefix);
+		let mut children = self.transport.get_children(dir, false)?;
+		children.retain(|child| child.starts_with(&prefix));
+		children.sort_by_key(|child| node_sequence(child));
+		for child in children.into_iter().rev() {
+			let path = format!("{}/{}", dir, child);
+			if let Some((Some(node), stat)) = self.read_node(&path, None)? {
+				if &node.owner == owner {
+					return Ok(Some((path, node, stat)));
+				}
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn write_node(
+		&self,
+		path: &str,
+		node: &NodeData,
+		version: Option<i32>,
+	) -> LockResult<Option<Stat>> {
+		match self.transport.set_data(path, node.encode(), version) {
+			Ok(stat) => Ok(Some(stat)),
+			Err(ZkError::BadVersion) => Ok(None),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Queue the owner of `config` as a `waiter-` ephemeral sequential node that is live until
+	/// `now + queue_timeout`, `true` if no live waiter has a lower sequence. Expired waiters are
+	/// removed.
+	fn enqueue(
+		&self,
+		config: &LockConfig,
+		now: DateTime<Utc>,
+		queue_timeout: Duration,
+	) -> LockResult<bool> {
+		let dir = self.queue_path(&config.name);
+		self.create_zk_path(&dir)?;
+
+		let node = NodeData::new(now, now + queue_timeout, config.owner(), 0);
+		let path = match self.find_node(&dir, WAITER_PREFIX, config.owner())? {
+			Some((path, _, stat)) => {
+				self.write_node(&path, &node, Some(stat.version))?;
+				path
+			}
+			None => self.transport.create(
+				&format!("{}/{}-", dir, WAITER_PREFIX),
+				node.encode(),
+				Acl::open_unsafe().clone(),
+				CreateMode::EphemeralSequential,
+			)?,
+		};
+		let sequence = node_sequence(&path)
+			.ok_or_else(|| LockError::InvalidLock(format!("invalid sequential node: {}", path)))?;
+
+		for child in self.transport.get_children(&dir, false)? {
+			if !child.starts_with(WAITER_PREFIX)
+				|| node_sequence(&child).map_or(true, |seq| seq >= sequence)
+			{
+				continue;
+			}
+
+			let child = format!("{}/{}", dir, child);
+			match self.read_node(&child, Some(config.max_lock))? {
+				Some((Some(other), _)) if other.lock_until > now => return Ok(false),
+				Some((_, stat)) => self.delete_node(&child, Some(stat.version))?,
+				None => {}
+			}
+		}
+
+		Ok(true)
+	}
+
 	pub fn create_zk_path(&self, path: &str) -> LockResult<()> {
 		let parts = path.split('/').filter(|p| !p.is_empty()).collect::<Vec<_>>();
 		let mut cur_path = String::new();
@@ -71,12 +215,19 @@ impl ZookeeperDriver<'_> {
 			cur_path.push_str(part);
 
 			if self.transport.exists(&cur_path, false)?.is_none() {
-				self.transport.create(
+				match self.transport.create(
 					&cur_path,
 					vec![],
 					Acl::open_unsafe().clone(),
 					CreateMode::Persistent,
-				)?;
+				) {
+					Ok(_) => {
+						event!(path = %cur_path, "created zookeeper path");
+					}
+					// created by another client in the meantime
+					Err(ZkError::NodeExists) => {}
+					Err(e) => return Err(e.into()),
+				}
 			}
 		}
 
@@ -87,43 +238,429 @@ impl ZookeeperDriver<'_> {
 impl Lockable for ZookeeperDriver<'_> {
 	fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
 		let path = self.path(&config.name);
-		if !self.check_locked(&path, config)? {
-			if self.transport.exists(&path, false)?.is_none() {
-				self.create_zk_path(&path)?;
+		let now = self.now(config)?;
+		let queue_timeout = config.queue_timeout.filter(|_| !config.reentrant);
+		if let Some(queue_timeout) = queue_timeout {
+			if !self.enqueue(config, now, queue_timeout)? {
+				return Ok(LockState::unlock());
 			}
+		}
 
-			let now = Utc::now();
-			let data = now.timestamp_millis().to_be_bytes().to_vec();
-			let _ = self.transport.set_data(&path, data, None)?;
-			Ok(LockState::new(true, now))
-		} else {
-			Ok(LockState::unlock())
+		loop {
+			let (version, holds) = match self.read_node(&path, Some(config.max_lock))? {
+				// an expired lock is only taken over once the drift allowance has passed too
+				Some((Some(node), stat)) if node.lock_until > now - config.drift => {
+					if !config.reentrant || &node.owner != config.owner() {
+						return Ok(LockState::unlock());
+					}
+					(Some(stat.version), node.holds + 1)
+				}
+				Some((_, stat)) => (Some(stat.version), 1),
+				// created empty, of clients racing on a new lock only the first write of
+				// version 0 wins
+				None => {
+					self.create_zk_path(&path)?;
+					(Some(0), 1)
+				}
+			};
+
+			let node = NodeData::new(now, now + config.max_lock, config.owner(), holds);
+			match self.write_node(&path, &node, version)? {
+				Some(stat) => {
+					if queue_timeout.is_some() {
+						self.leave_queue(config)?;
+					}
+					let state = LockState::new(true, config.now()).with_token(stat.mzxid);
+					return Ok(state.with_holds(holds));
+				}
+				// another lock of the same owner changed the hold count, count again
+				None if holds > 1 => continue,
+				None => return Ok(LockState::unlock()),
+			}
 		}
 	}
 
 	fn release_lock(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
 		let path = self.path(&config.name);
-		let at_least_until = config.lock_at_least_until(state.locked_at);
-		if at_least_until > Utc::now() {
-			let data = at_least_until.timestamp_millis().to_be_bytes().to_vec();
-			let _ = self.transport.set_data(&path, data, None)?;
+		let stat = loop {
+			let (node, stat) = match self.read_node(&path, Some(config.max_lock))? {
+				Some((Some(node), stat)) if &node.owner == config.owner() => (node, stat),
+				_ => return Ok(LockState::unlock()),
+			};
+
+			if !config.reentrant || node.holds <= 1 {
+				break stat;
+			}
+
+			let holds = node.holds - 1;
+			let node = NodeData::new(node.locked_at, node.lock_until, config.owner(), holds);
+			if self.write_node(&path, &node, Some(stat.version))?.is_some() {
+				return Ok(state.with_holds(holds));
+			}
+		};
+
+		let remaining = config.at_least_remaining(state.locked_at);
+		if remaining > Duration::zero() {
+			let lock_until = self.now(config)? + remaining;
+			let node = NodeData::new(state.locked_at, lock_until, config.owner(), 0);
+			self.write_node(&path, &node, Some(stat.version))?;
 			Ok(*state)
 		} else {
-			if self.transport.exists(&path, false)?.is_some() {
-				self.transport.delete(&path, None)?;
-			}
+			self.delete_node(&path, Some(stat.version))?;
 			Ok(LockState::unlock())
 		}
 	}
 
 	fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
 		let path = self.path(&config.name);
-		if self.check_locked(&path, config)? {
-			let data = Utc::now().timestamp_millis().to_be_bytes().to_vec();
-			let _ = self.transport.set_data(&path, data, None)?;
-			Ok(LockState::new(true, Utc::now()))
+		let now = self.now(config)?;
+		let (holds, stat) = match self.read_node(&path, Some(config.max_lock))? {
+			Some((Some(node), stat)) if node.lock_until > now && &node.owner == config.owner() => {
+				(node.holds, stat)
+			}
+			_ => return Ok(LockState::unlock()),
+		};
+
+		let node = NodeData::new(now, now + config.max_lock, config.owner(), holds);
+		match self.write_node(&path, &node, Some(stat.version))? {
+			Some(stat) => {
+				Ok(LockState::new(true, config.now()).with_token(stat.mzxid).with_holds(holds))
+			}
+			None => Ok(LockState::unlock()),
+		}
+	}
+
+	fn leave_queue(&self, config: &LockConfig) -> LockResult<()> {
+		let dir = self.queue_path(&config.name);
+		match self.find_node(&dir, WAITER_PREFIX, config.owner()) {
+			Ok(Some((path, _, stat))) => self.delete_node(&path, Some(stat.version)),
+			Ok(None) | Err(LockError::ZkError(ZkError::NoNode)) => Ok(()),
+			Err(e) => Err(e),
+		}
+	}
+
+	fn inspect(&self, config: &LockConfig) -> LockResult<Option<LockInfo>> {
+		match self.read_node(&self.path(&config.name), Some(config.max_lock))? {
+			Some((Some(node), stat)) if node.lock_until > self.now(config)? => {
+				Ok(Some(node.into_info(&config.name).with_token(stat.mzxid)))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	fn watch_lost(&self, signal: &LostSignal) {
+		let signal = signal.clone();
+		let subscription = self.transport.add_listener(move |state| {
+			if matches!(state, ZkState::Closed | ZkState::AuthFailed) {
+				signal.fire(LostReason::SessionExpired);
+			}
+		});
+
+		let mut current = self.subscription.lock().unwrap_or_else(PoisonError::into_inner);
+		if let Some(previous) = current.replace(subscription) {
+			self.transport.remove_listener(previous);
+		}
+	}
+
+	fn provider(&self) -> &'static str {
+		"zookeeper"
+	}
+}
+
+impl LockAdmin for ZookeeperDriver<'_> {
+	fn list_locks(&self) -> LockResult<Vec<LockInfo>> {
+		let names = match self.transport.get_children(&self.parent, false) {
+			Ok(names) => names,
+			Err(ZkError::NoNode) => return Ok(vec![]),
+			Err(e) => return Err(e.into()),
+		};
+
+		let now = Utc::now();
+		let mut locks = Vec::with_capacity(names.len());
+		for name in names {
+			// queue parents of fair locks have no data
+			if let Some((Some(node), stat)) = self.read_node(&self.path(&name), None)? {
+				if node.lock_until > now {
+					locks.push(node.into_info(&name).with_token(stat.mzxid));
+				}
+			}
+		}
+		Ok(locks)
+	}
+
+	fn force_unlock(&self, name: &str) -> LockResult<bool> {
+		let path = self.path(name);
+		loop {
+			let stat = match self.read_node(&path, None)? {
+				Some((Some(node), stat)) if node.lock_until > Utc::now() => stat,
+				_ => return Ok(false),
+			};
+
+			match self.transport.delete(&path, Some(stat.version)) {
+				Ok(_) => return Ok(true),
+				Err(ZkError::NoNode) => return Ok(false),
+				// renewed in the meantime, look again
+				Err(ZkError::BadVersion) => continue,
+				Err(e) => return Err(e.into()),
+			}
+		}
+	}
+}
+
+/// Read-write holds are `read-`/`write-` persistent sequential nodes under the lock path, carrying
+/// the same data as a lock node. A hold is granted when no live conflicting node has a lower
+/// sequence, otherwise the new node is removed again. The sequence is the fencing token.
+impl RwLockable for ZookeeperDriver<'_> {
+	fn acquire_rw_lock(&self, config: &LockConfig, mode: RwMode) -> LockResult<LockState> {
+		let dir = self.path(&config.name);
+		self.create_zk_path(&dir)?;
+
+		let now = config.now();
+		let node = NodeData::new(now, now + config.max_lock, config.owner(), 1);
+		let path = self.transport.create(
+			&format!("{}/{}-", dir, mode),
+			node.encode(),
+			Acl::open_unsafe().clone(),
+			CreateMode::PersistentSequential,
+		)?;
+		let sequence = node_sequence(&path)
+			.ok_or_else(|| LockError::InvalidLock(format!("invalid sequential node: {}", path)))?;
+
+		for child in self.transport.get_children(&dir, false)? {
+			let conflict = match mode {
+				RwMode::Read => child.starts_with("write-"),
+				RwMode::Write => child.starts_with("write-") || child.starts_with("read-"),
+			};
+			if !conflict || node_sequence(&child).map_or(true, |seq| seq >= sequence) {
+				continue;
+			}
+
+			let child = format!("{}/{}", dir, child);
+			match self.read_node(&child, Some(config.max_lock))? {
+				Some((Some(other), _)) if other.lock_until > now => {
+					self.delete_node(&path, None)?;
+					return Ok(LockState::unlock());
+				}
+				Some((_, stat)) => self.delete_node(&child, Some(stat.version))?,
+				None => {}
+			}
+		}
+
+		Ok(LockState::new(true, now).with_token(sequence))
+	}
+
+	fn release_rw_lock(
+		&self,
+		config: &LockConfig,
+		mode: RwMode,
+		state: &LockState,
+	) -> LockResult<LockState> {
+		let dir = self.path(&config.name);
+		let Some((path, node, stat)) = self.find_node(&dir, mode.as_str(), config.owner())? else {
+			return Ok(LockState::unlock());
+		};
+
+		let at_least_until = config.lock_at_least_until(state.locked_at);
+		if at_least_until > config.now() {
+			let node = NodeData::new(node.locked_at, at_least_until, config.owner(), 0);
+			self.write_node(&path, &node, Some(stat.version))?;
 		} else {
-			Ok(LockState::unlock())
+			self.delete_node(&path, Some(stat.version))?;
 		}
+
+		Ok(LockState::unlock())
+	}
+
+	fn extend_rw_lock(&self, config: &LockConfig, mode: RwMode) -> LockResult<LockState> {
+		let dir = self.path(&config.name);
+		let now = config.now();
+		let (path, stat) = match self.find_node(&dir, mode.as_str(), config.owner())? {
+			Some((path, node, stat)) if node.lock_until > now => (path, stat),
+			_ => return Ok(LockState::unlock()),
+		};
+
+		let node = NodeData::new(now, now + config.max_lock, config.owner(), 1);
+		match self.write_node(&path, &node, Some(stat.version))? {
+			Some(_) => Ok(LockState::new(true, now).with_token(node_sequence(&path).unwrap_or(0))),
+			None => Ok(LockState::unlock()),
+		}
+	}
+}
+
+/// The client has no `multi()`, names are locked one by one and rolled back on failure.
+impl MultiLockable for ZookeeperDriver<'_> {
+	fn acquire_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+		acquire_each(self, configs)
+	}
+
+	fn release_locks(
+		&self,
+		configs: &[LockConfig],
+		states: &[LockState],
+	) -> LockResult<Vec<LockState>> {
+		release_each(self, configs, states)
+	}
+
+	fn extend_locks(&self, configs: &[LockConfig]) -> LockResult<Vec<LockState>> {
+		extend_each(self, configs)
+	}
+}
+
+/// Semaphore permits are `permit-` ephemeral sequential nodes under the lock path, so the permit of
+/// a crashed worker goes away with its session or once its lease runs out. A permit is granted
+/// when fewer than `permits` live nodes have a lower sequence, which is the fencing token.
+impl SemaphoreLockable for ZookeeperDriver<'_> {
+	fn acquire_permit(&self, config: &LockConfig, permits: u32) -> LockResult<LockState> {
+		let dir = self.path(&config.name);
+		self.create_zk_path(&dir)?;
+
+		let now = config.now();
+		let node = NodeData::new(now, now + config.max_lock, config.owner(), 1);
+		let path = self.transport.create(
+			&format!("{}/{}-", dir, PERMIT_PREFIX),
+			node.encode(),
+			Acl::open_unsafe().clone(),
+			CreateMode::EphemeralSequential,
+		)?;
+		let sequence = node_sequence(&path)
+			.ok_or_else(|| LockError::InvalidLock(format!("invalid sequential node: {}", path)))?;
+
+		let mut taken = 0;
+		for child in self.transport.get_children(&dir, false)? {
+			if !child.starts_with(PERMIT_PREFIX)
+				|| node_sequence(&child).map_or(true, |seq| seq >= sequence)
+			{
+				continue;
+			}
+
+			let child = format!("{}/{}", dir, child);
+			match self.read_node(&child, Some(config.max_lock))? {
+				Some((Some(other), _)) if other.lock_until > now => taken += 1,
+				Some((_, stat)) => self.delete_node(&child, Some(stat.version))?,
+				None => {}
+			}
+		}
+
+		if taken >= permits {
+			self.delete_node(&path, None)?;
+			return Ok(LockState::unlock());
+		}
+
+		Ok(LockState::new(true, now).with_token(sequence))
+	}
+
+	fn release_permit(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+		let dir = self.path(&config.name);
+		let Some((path, node, stat)) = self.find_node(&dir, PERMIT_PREFIX, config.owner())? else {
+			return Ok(LockState::unlock());
+		};
+
+		let at_least_until = config.lock_at_least_until(state.locked_at);
+		if at_least_until > config.now() {
+			let node = NodeData::new(node.locked_at, at_least_until, config.owner(), 0);
+			self.write_node(&path, &node, Some(stat.version))?;
+		} else {
+			self.delete_node(&path, Some(stat.version))?;
+		}
+
+		Ok(LockState::unlock())
+	}
+
+	fn extend_permit(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+		let dir = self.path(&config.name);
+		let now = config.now();
+		let (path, stat) = match self.find_node(&dir, PERMIT_PREFIX, config.owner())? {
+			Some((path, node, stat)) if node.lock_until > now => (path, stat),
+			_ => return Ok(LockState::unlock()),
+		};
+
+		let node = NodeData::new(now, now + config.max_lock, config.owner(), 1);
+		match self.write_node(&path, &node, Some(stat.version))? {
+			Some(_) => Ok(LockState::new(true, now).with_token(state.token)),
+			None => Ok(LockState::unlock()),
+		}
+	}
+}
+
+/// Sequence suffix of a sequential node name or path.
+fn node_sequence(name: &str) -> Option<i64> {
+	name.rsplit('-').next().and_then(|seq| seq.parse().ok())
+}
+
+impl Drop for ZookeeperDriver<'_> {
+	fn drop(&mut self) {
+		let subscription = self.subscription.get_mut().unwrap_or_else(PoisonError::into_inner);
+		if let Some(subscription) = subscription.take() {
+			self.transport.remove_listener(subscription);
+		}
+	}
+}
+
+/// Lock node data: `locked_at` and `lock_until` as big-endian millis and the hold count as a
+/// big-endian u32, followed by the owner id. Nodes of earlier versions only hold the lock time in
+/// millis and were held until `max_lock` after it, they decode as a lock of no owner.
+struct NodeData {
+	locked_at: DateTime<Utc>,
+	lock_until: DateTime<Utc>,
+	holds: u32,
+	owner: LockOwner,
+}
+
+impl NodeData {
+	fn new(
+		locked_at: DateTime<Utc>,
+		lock_until: DateTime<Utc>,
+		owner: &LockOwner,
+		holds: u32,
+	) -> NodeData {
+		NodeData { locked_at, lock_until, holds, owner: owner.clone() }
+	}
+
+	fn into_info(self, name: &str) -> LockInfo {
+		LockInfo::new(name, self.owner, self.locked_at, self.lock_until).with_holds(self.holds)
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let mut data = Vec::with_capacity(20 + self.owner.id().len());
+		data.extend_from_slice(&self.locked_at.timestamp_millis().to_be_bytes());
+		data.extend_from_slice(&self.lock_until.timestamp_millis().to_be_bytes());
+		data.extend_from_slice(&self.holds.to_be_bytes());
+		data.extend_from_slice(self.owner.id().as_bytes());
+		data
+	}
+
+	/// Decode node data, a legacy node expires `max_lock` after its lock time, or never without
+	/// a `max_lock` as its lease is unknown then.
+	fn decode(data: &[u8], max_lock: Option<Duration>) -> LockResult<Option<NodeData>> {
+		if data.is_empty() {
+			return Ok(None);
+		}
+
+		if data.len() == LEGACY_NODE_LEN {
+			let locked_at = Self::decode_millis(data)?;
+			let lock_until = max_lock
+				.and_then(|max_lock| locked_at.checked_add_signed(max_lock))
+				.unwrap_or(DateTime::<Utc>::MAX_UTC);
+			let owner = LockOwner::from_id(String::new());
+			return Ok(Some(NodeData { locked_at, lock_until, holds: 0, owner }));
+		}
+
+		if data.len() < 20 {
+			return Err(LockError::InvalidLock("can't parse zk data to lock node".to_string()));
+		}
+
+		let locked_at = Self::decode_millis(&data[..8])?;
+		let lock_until = Self::decode_millis(&data[8..16])?;
+		let holds = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+		let owner = LockOwner::from_id(String::from_utf8_lossy(&data[20..]));
+		Ok(Some(NodeData { locked_at, lock_until, holds, owner }))
+	}
+
+	fn decode_millis(bytes: &[u8]) -> LockResult<DateTime<Utc>> {
+		let ts =
+			i64::from_be_bytes(bytes.try_into().map_err(|_| {
+				LockError::InvalidLock("can't parse zk data to timestamp".to_string())
+			})?);
+		from_millis(ts)
 	}
 }
@@ -0,0 +1,157 @@
+use chrono::Duration;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use std::future::Future;
+use std::time::Instant;
+
+use crate::core::random_u64;
+use crate::error::LockError;
+use crate::error::LockResult;
+
+/// Delay between two attempts of `DistLock::acquire_with`.
+#[derive(Debug, Clone, Copy)]
+pub enum Backoff {
+	Fixed(Duration),
+	/// Doubles from `initial` up to `max`, each delay is jittered to `[delay / 2, delay]`.
+	Exponential {
+		initial: Duration,
+		max: Duration,
+	},
+}
+
+/// Retry policy for a contended lock, unbounded unless `max_attempts` or `timeout` is set.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+	pub(crate) backoff: Backoff,
+	pub(crate) max_attempts: Option<u32>,
+	pub(crate) timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+	pub fn fixed(interval: Duration) -> RetryPolicy {
+		RetryPolicy { backoff: Backoff::Fixed(interval), max_attempts: None, timeout: None }
+	}
+
+	pub fn exponential(initial: Duration, max: Duration) -> RetryPolicy {
+		RetryPolicy {
+			backoff: Backoff::Exponential { initial, max },
+			max_attempts: None,
+			timeout: None,
+		}
+	}
+
+	pub fn max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+		self.max_attempts = Some(max_attempts);
+		self
+	}
+
+	/// Give up after `timeout` of real time, measured on the monotonic clock of the host as the
+	/// delays are slept in real time too. The clock of the lock config only drives lease times.
+	pub fn timeout(mut self, timeout: Duration) -> RetryPolicy {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	pub fn backoff(&self) -> &Backoff {
+		&self.backoff
+	}
+
+	/// Delay before the attempt following `attempt`, which starts at 1.
+	pub fn delay(&self, attempt: u32) -> Duration {
+		match self.backoff {
+			Backoff::Fixed(interval) => interval,
+			Backoff::Exponential { initial, max } => {
+				let factor = 2_i64.saturating_pow(attempt.saturating_sub(1));
+				let delay = initial.num_milliseconds().saturating_mul(factor);
+				let half = delay.min(max.num_milliseconds()).max(0) / 2;
+				let jitter = (random_u64() % (half as u64 + 1)) as i64;
+				Duration::milliseconds(half + jitter)
+			}
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Delay before the attempt following `attempt`, clamped to `deadline`, or the error to give
+	/// up with.
+	fn next_delay(&self, attempt: u32, deadline: Option<Instant>) -> LockResult<Duration> {
+		if self.max_attempts.is_some_and(|max| attempt >= max) {
+			return Err(LockError::LockFailed);
+		}
+
+		let mut delay = self.delay(attempt);
+		if let Some(deadline) = deadline {
+			let left = deadline.saturating_duration_since(Instant::now());
+			if left.is_zero() {
+				return Err(LockError::Timeout);
+			}
+			delay = delay.min(Duration::from_std(left).unwrap_or(Duration::max_value()));
+		}
+		Ok(delay)
+	}
+
+	/// End of the timeout if it starts now, `None` if there is none or it is beyond reach.
+	fn deadline(&self) -> Option<Instant> {
+		let timeout = self.timeout?.to_std().unwrap_or_default();
+		Instant::now().checked_add(timeout)
+	}
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self::fixed(Duration::milliseconds(100))
+	}
+}
+
+pub(crate) fn sleep(duration: Duration) {
+	std::thread::sleep(duration.to_std().unwrap_or_default());
+}
+
+cfg_if::cfg_if! {
+	if #[cfg(feature = "tokio")] {
+		pub(crate) async fn sleep_async(duration: Duration) {
+			tokio::time::sleep(duration.to_std().unwrap_or_default()).await;
+		}
+	} else if #[cfg(feature = "async-std")] {
+		pub(crate) async fn sleep_async(duration: Duration) {
+			async_std::task::sleep(duration.to_std().unwrap_or_default()).await;
+		}
+	}
+}
+
+/// Call `acquire` until it takes the lock, sleeping between attempts as `policy` says. Fails with
+/// `LockError::Timeout` once the policy timeout has passed or `LockError::LockFailed` once its
+/// attempts are used up.
+pub(crate) fn retry<F>(policy: RetryPolicy, mut acquire: F) -> LockResult<()>
+where
+	F: FnMut() -> LockResult<bool>,
+{
+	let deadline = policy.deadline();
+	let mut attempt = 0;
+	loop {
+		attempt += 1;
+		if acquire()? {
+			return Ok(());
+		}
+
+		sleep(policy.next_delay(attempt, deadline)?);
+	}
+}
+
+/// Async version of `retry`.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) async fn retry_async<F, Fut>(policy: RetryPolicy, mut acquire: F) -> LockResult<()>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = LockResult<bool>>,
+{
+	let deadline = policy.deadline();
+	let mut attempt = 0;
+	loop {
+		attempt += 1;
+		if acquire().await? {
+			return Ok(());
+		}
+
+		sleep_async(policy.next_delay(attempt, deadline)?).await;
+	}
+}
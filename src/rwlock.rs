@@ -0,0 +1,308 @@
+//! A distributed read-write lock: any number of readers or a single writer.
+//!
+//! Readers and writers are tracked per owner in the backend, `min_lock`/`max_lock` of the
+//! `LockConfig` apply to every hold the same way they do for `DistLock`.
+use std::fmt::Display;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::block_on;
+use crate::core::LockConfig;
+use crate::core::LockOwner;
+use crate::core::LockState;
+use crate::core::PendingAcquire;
+use crate::core::StateCell;
+use crate::core::StateOpr;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::retry::retry;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::retry::retry_async;
+use crate::retry::RetryPolicy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RwMode {
+	Read,
+	Write,
+}
+
+impl RwMode {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			RwMode::Read => "read",
+			RwMode::Write => "write",
+		}
+	}
+}
+
+impl Display for RwMode {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+macro_rules! impl_rw_lockable {
+	($(#[$meta: meta])* $lockable: ident, $($async: ident)?) => {
+		$(#[$meta])*
+		pub trait $lockable {
+			$($async)? fn acquire_rw_lock(&self, config: &LockConfig, mode: RwMode)
+				-> LockResult<LockState>;
+
+			$($async)? fn release_rw_lock(
+				&self,
+				config: &LockConfig,
+				mode: RwMode,
+				state: &LockState,
+			) -> LockResult<LockState>;
+
+			$($async)? fn extend_rw_lock(&self, config: &LockConfig, mode: RwMode)
+				-> LockResult<LockState>;
+		}
+	};
+}
+
+impl_rw_lockable!(
+	/// A blocking read-write lock provider.
+	RwLockable,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_rw_lockable!(
+	/// An async read-write lock provider, available with the `tokio` or `async-std` feature.
+	#[async_trait::async_trait]
+	AsyncRwLockable,
+	async
+);
+
+macro_rules! impl_dist_rw_lock {
+	(
+		$(#[$meta: meta])*
+		$lock: ident,
+		$guard: ident,
+		$lockable: ident,
+		$retry: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		#[derive(Debug)]
+		pub struct $lock<T: $lockable> {
+			config: LockConfig,
+			driver: T,
+			read: StateCell,
+			write: StateCell,
+			create_at: DateTime<Utc>,
+		}
+
+		impl<T: $lockable> $lock<T> {
+			pub fn new(mut config: LockConfig, driver: T) -> Self {
+				if config.owner.is_none() {
+					config.owner = Some(LockOwner::new());
+				}
+
+				let create_at = config.now();
+				$lock {
+					config,
+					driver,
+					read: Default::default(),
+					write: Default::default(),
+					create_at,
+				}
+			}
+
+			pub fn driver(&self) -> &T {
+				&self.driver
+			}
+
+			pub fn config(&self) -> &LockConfig {
+				&self.config
+			}
+
+			pub fn state(&self, mode: RwMode) -> LockState {
+				self.cell(mode).get()
+			}
+
+			pub fn create_at(&self) -> &DateTime<Utc> {
+				&self.create_at
+			}
+
+			/// Acquire a read hold and return a guard that releases it on drop, `None` if a
+			/// writer holds the lock.
+			pub $($async)? fn read(&self) -> LockResult<Option<$guard<'_, T>>> {
+				self.try_guard(RwMode::Read)$($await)*
+			}
+
+			/// Acquire the write hold and return a guard that releases it on drop, `None` if
+			/// any reader or another writer holds the lock.
+			pub $($async)? fn write(&self) -> LockResult<Option<$guard<'_, T>>> {
+				self.try_guard(RwMode::Write)$($await)*
+			}
+
+			/// Retry `read` following `policy`, see `DistLock::acquire_with`.
+			pub $($async)? fn read_with(&self, policy: RetryPolicy) -> LockResult<$guard<'_, T>> {
+				self.acquire_with(RwMode::Read, policy)$($await)*?;
+				Ok($guard { lock: self, mode: RwMode::Read })
+			}
+
+			/// Retry `write` following `policy`, see `DistLock::acquire_with`.
+			pub $($async)? fn write_with(&self, policy: RetryPolicy) -> LockResult<$guard<'_, T>> {
+				self.acquire_with(RwMode::Write, policy)$($await)*?;
+				Ok($guard { lock: self, mode: RwMode::Write })
+			}
+
+			pub $($async)? fn acquire(&self, mode: RwMode) -> LockResult<bool> {
+				let cell = self.cell(mode);
+				if cell.get().check_locked(&self.config) {
+					return Ok(true);
+				}
+
+				let pending = PendingAcquire { state: cell };
+				let result = self.driver.acquire_rw_lock(&self.config, mode)$($await)*;
+				std::mem::forget(pending);
+
+				let state = result?;
+				cell.set(state);
+				Ok(state.is_locked)
+			}
+
+			pub $($async)? fn acquire_with(&self, mode: RwMode, policy: RetryPolicy) -> LockResult<()> {
+				$retry(policy, move || self.acquire(mode))$($await)*
+			}
+
+			pub $($async)? fn release(&self, mode: RwMode) -> LockResult<()> {
+				let cell = self.cell(mode);
+				if !cell.get().is_locked {
+					return Ok(());
+				}
+
+				let result = self.driver.release_rw_lock(&self.config, mode, &cell.get())$($await)*;
+				if matches!(result, Err(LockError::NotOwner(_))) {
+					cell.set(LockState::unlock());
+				}
+
+				cell.set(result?);
+				Ok(())
+			}
+
+			pub $($async)? fn extend(&self, mode: RwMode) -> LockResult<bool> {
+				let cell = self.cell(mode);
+				if !cell.get().is_locked {
+					return Ok(false);
+				}
+
+				let result = self.driver.extend_rw_lock(&self.config, mode)$($await)*;
+				if matches!(result, Err(LockError::NotOwner(_))) {
+					cell.set(LockState::unlock());
+				}
+
+				let state = result?;
+				cell.set(state);
+				Ok(state.is_locked)
+			}
+
+			$($async)? fn try_guard(&self, mode: RwMode) -> LockResult<Option<$guard<'_, T>>> {
+				if self.acquire(mode)$($await)*? {
+					Ok(Some($guard { lock: self, mode }))
+				} else {
+					Ok(None)
+				}
+			}
+
+			fn cell(&self, mode: RwMode) -> &StateCell {
+				match mode {
+					RwMode::Read => &self.read,
+					RwMode::Write => &self.write,
+				}
+			}
+		}
+
+		impl<T: $lockable> Drop for $lock<T> {
+			fn drop(&mut self) {
+				self.release_on_drop(RwMode::Write);
+				self.release_on_drop(RwMode::Read);
+			}
+		}
+
+		/// A read or write hold on a read-write lock, released when the guard is dropped.
+		#[derive(Debug)]
+		#[must_use = "if unused the lock will be released immediately"]
+		pub struct $guard<'a, T: $lockable> {
+			lock: &'a $lock<T>,
+			mode: RwMode,
+		}
+
+		impl<T: $lockable> $guard<'_, T> {
+			pub fn lock(&self) -> &$lock<T> {
+				self.lock
+			}
+
+			pub fn mode(&self) -> RwMode {
+				self.mode
+			}
+
+			pub fn state(&self) -> LockState {
+				self.lock.state(self.mode)
+			}
+
+			pub fn fencing_token(&self) -> i64 {
+				self.state().token
+			}
+
+			pub $($async)? fn extend(&self) -> LockResult<bool> {
+				self.lock.extend(self.mode)$($await)*
+			}
+
+			/// Release the lock now, the guard does not release it again when dropped.
+			pub $($async)? fn release(self) -> LockResult<()> {
+				let (lock, mode) = (self.lock, self.mode);
+				std::mem::forget(self);
+				lock.release(mode)$($await)*
+			}
+		}
+
+		impl<T: $lockable> Drop for $guard<'_, T> {
+			fn drop(&mut self) {
+				self.lock.release_on_drop(self.mode);
+			}
+		}
+	};
+}
+
+impl_dist_rw_lock!(
+	/// A distributed read-write lock backed by a blocking `RwLockable` driver.
+	DistRwLock,
+	RwLockGuard,
+	RwLockable,
+	retry,
+	,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_dist_rw_lock!(
+	/// A distributed read-write lock backed by an `AsyncRwLockable` driver.
+	AsyncDistRwLock,
+	AsyncRwLockGuard,
+	AsyncRwLockable,
+	retry_async,
+	async,
+	.await
+);
+
+impl<T: RwLockable> DistRwLock<T> {
+	fn release_on_drop(&self, mode: RwMode) {
+		if self.cell(mode).get().is_locked {
+			let _ = self.release(mode);
+		}
+	}
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl<T: AsyncRwLockable> AsyncDistRwLock<T> {
+	fn release_on_drop(&self, mode: RwMode) {
+		if self.cell(mode).get().is_locked {
+			let _ = block_on(self.release(mode));
+		}
+	}
+}
@@ -0,0 +1,256 @@
+//! A distributed counting semaphore that lets up to `permits` owners in at the same time.
+//!
+//! Every permit is a lease of its own: `min_lock`/`max_lock` of the `LockConfig` apply to each
+//! permit, and the permit of a crashed worker is reclaimed once its lease runs out.
+use chrono::DateTime;
+use chrono::Utc;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::core::block_on;
+use crate::core::LockConfig;
+use crate::core::LockOwner;
+use crate::core::LockState;
+use crate::core::PendingAcquire;
+use crate::core::StateCell;
+use crate::core::StateOpr;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::retry::retry;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::retry::retry_async;
+use crate::retry::RetryPolicy;
+
+macro_rules! impl_semaphore_lockable {
+	($(#[$meta: meta])* $lockable: ident, $($async: ident)?) => {
+		$(#[$meta])*
+		pub trait $lockable {
+			/// Take one of `permits` permits for the owner of `config`.
+			$($async)? fn acquire_permit(&self, config: &LockConfig, permits: u32)
+				-> LockResult<LockState>;
+
+			$($async)? fn release_permit(
+				&self,
+				config: &LockConfig,
+				state: &LockState,
+			) -> LockResult<LockState>;
+
+			/// Renew the lease of the permit, the returned state keeps the token of `state`.
+			$($async)? fn extend_permit(
+				&self,
+				config: &LockConfig,
+				state: &LockState,
+			) -> LockResult<LockState>;
+		}
+	};
+}
+
+impl_semaphore_lockable!(
+	/// A blocking semaphore provider.
+	SemaphoreLockable,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_semaphore_lockable!(
+	/// An async semaphore provider, available with the `tokio` or `async-std` feature.
+	#[async_trait::async_trait]
+	AsyncSemaphoreLockable,
+	async
+);
+
+macro_rules! impl_dist_semaphore {
+	(
+		$(#[$meta: meta])*
+		$semaphore: ident,
+		$permit: ident,
+		$lockable: ident,
+		$retry: ident,
+		$($async: ident)?,
+		$($await: tt)*
+	) => {
+		$(#[$meta])*
+		#[derive(Debug)]
+		pub struct $semaphore<T: $lockable> {
+			config: LockConfig,
+			permits: u32,
+			driver: T,
+			state: StateCell,
+			create_at: DateTime<Utc>,
+		}
+
+		impl<T: $lockable> $semaphore<T> {
+			pub fn new(mut config: LockConfig, permits: u32, driver: T) -> Self {
+				if config.owner.is_none() {
+					config.owner = Some(LockOwner::new());
+				}
+
+				let create_at = config.now();
+				$semaphore { config, permits, driver, state: Default::default(), create_at }
+			}
+
+			pub fn driver(&self) -> &T {
+				&self.driver
+			}
+
+			pub fn config(&self) -> &LockConfig {
+				&self.config
+			}
+
+			pub fn permits(&self) -> u32 {
+				self.permits
+			}
+
+			pub fn state(&self) -> LockState {
+				self.state.get()
+			}
+
+			pub fn create_at(&self) -> &DateTime<Utc> {
+				&self.create_at
+			}
+
+			/// Take a permit and return a guard that gives it back on drop, `None` if all
+			/// permits are taken.
+			pub $($async)? fn try_permit(&self) -> LockResult<Option<$permit<'_, T>>> {
+				if self.acquire()$($await)*? {
+					Ok(Some($permit { semaphore: self }))
+				} else {
+					Ok(None)
+				}
+			}
+
+			/// Retry `try_permit` following `policy`, see `DistLock::acquire_with`.
+			pub $($async)? fn permit_with(&self, policy: RetryPolicy) -> LockResult<$permit<'_, T>> {
+				self.acquire_with(policy)$($await)*?;
+				Ok($permit { semaphore: self })
+			}
+
+			pub $($async)? fn acquire(&self) -> LockResult<bool> {
+				if self.state.get().check_locked(&self.config) {
+					return Ok(true);
+				}
+
+				let pending = PendingAcquire { state: &self.state };
+				let result = self.driver.acquire_permit(&self.config, self.permits)$($await)*;
+				std::mem::forget(pending);
+
+				let state = result?;
+				self.state.set(state);
+				Ok(state.is_locked)
+			}
+
+			pub $($async)? fn acquire_with(&self, policy: RetryPolicy) -> LockResult<()> {
+				$retry(policy, move || self.acquire())$($await)*
+			}
+
+			pub $($async)? fn release(&self) -> LockResult<()> {
+				if !self.state.get().is_locked {
+					return Ok(());
+				}
+
+				let result = self.driver.release_permit(&self.config, &self.state.get())$($await)*;
+				if matches!(result, Err(LockError::NotOwner(_))) {
+					self.state.set(LockState::unlock());
+				}
+
+				self.state.set(result?);
+				Ok(())
+			}
+
+			pub $($async)? fn extend(&self) -> LockResult<bool> {
+				let state = self.state.get();
+				if !state.is_locked {
+					return Ok(false);
+				}
+
+				let result = self.driver.extend_permit(&self.config, &state)$($await)*;
+				if matches!(result, Err(LockError::NotOwner(_))) {
+					self.state.set(LockState::unlock());
+				}
+
+				let state = result?;
+				self.state.set(state);
+				Ok(state.is_locked)
+			}
+		}
+
+		impl<T: $lockable> Drop for $semaphore<T> {
+			fn drop(&mut self) {
+				self.release_on_drop();
+			}
+		}
+
+		/// A permit of a semaphore, given back when the guard is dropped.
+		#[derive(Debug)]
+		#[must_use = "if unused the permit will be released immediately"]
+		pub struct $permit<'a, T: $lockable> {
+			semaphore: &'a $semaphore<T>,
+		}
+
+		impl<T: $lockable> $permit<'_, T> {
+			pub fn semaphore(&self) -> &$semaphore<T> {
+				self.semaphore
+			}
+
+			pub fn state(&self) -> LockState {
+				self.semaphore.state()
+			}
+
+			pub fn fencing_token(&self) -> i64 {
+				self.semaphore.state().token
+			}
+
+			pub $($async)? fn extend(&self) -> LockResult<bool> {
+				self.semaphore.extend()$($await)*
+			}
+
+			/// Return the permit now, it is not released again when dropped.
+			pub $($async)? fn release(self) -> LockResult<()> {
+				let semaphore = self.semaphore;
+				std::mem::forget(self);
+				semaphore.release()$($await)*
+			}
+		}
+
+		impl<T: $lockable> Drop for $permit<'_, T> {
+			fn drop(&mut self) {
+				self.semaphore.release_on_drop();
+			}
+		}
+	};
+}
+
+impl_dist_semaphore!(
+	/// A distributed semaphore backed by a blocking `SemaphoreLockable` driver.
+	DistSemaphore,
+	Permit,
+	SemaphoreLockable,
+	retry,
+	,
+);
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl_dist_semaphore!(
+	/// A distributed semaphore backed by an `AsyncSemaphoreLockable` driver.
+	AsyncDistSemaphore,
+	AsyncPermit,
+	AsyncSemaphoreLockable,
+	retry_async,
+	async,
+	.await
+);
+
+impl<T: SemaphoreLockable> DistSemaphore<T> {
+	fn release_on_drop(&self) {
+		if self.state.get().is_locked {
+			let _ = self.release();
+		}
+	}
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+impl<T: AsyncSemaphoreLockable> AsyncDistSemaphore<T> {
+	fn release_on_drop(&self) {
+		if self.state.get().is_locked {
+			let _ = block_on(self.release());
+		}
+	}
+}
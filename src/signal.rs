@@ -0,0 +1,133 @@
+//! Notification for code running inside a critical section that its lock is gone.
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LostReason {
+	/// The lease ran out before it was extended.
+	Expired,
+	/// The backend reports another owner.
+	NotOwner,
+	/// The watchdog failed to extend the lease.
+	RenewalFailed,
+	/// The backend session ended, e.g. an expired ZooKeeper session.
+	SessionExpired,
+}
+
+type Callback = Arc<dyn Fn(LostReason) + Send + Sync>;
+
+#[derive(Default)]
+struct SignalState {
+	reason: Option<LostReason>,
+	wakers: Vec<Waker>,
+	senders: Vec<Sender<LostReason>>,
+	callbacks: Vec<Callback>,
+}
+
+/// Fires once when a held lock is lost, and is re-armed when the lock is acquired again.
+///
+/// Async code awaits `lost()` like a cancellation token, sync code registers a callback with
+/// `on_lost` or receives from `subscribe`.
+#[derive(Clone, Default)]
+pub struct LostSignal {
+	state: Arc<Mutex<SignalState>>,
+}
+
+impl LostSignal {
+	pub fn new() -> LostSignal {
+		Self::default()
+	}
+
+	pub fn is_lost(&self) -> bool {
+		self.lock().reason.is_some()
+	}
+
+	pub fn reason(&self) -> Option<LostReason> {
+		self.lock().reason
+	}
+
+	/// Call `callback` every time the signal fires.
+	pub fn on_lost<F>(&self, callback: F)
+	where
+		F: Fn(LostReason) + Send + Sync + 'static,
+	{
+		self.lock().callbacks.push(Arc::new(callback));
+	}
+
+	/// Receive a message every time the signal fires.
+	pub fn subscribe(&self) -> Receiver<LostReason> {
+		let (sender, receiver) = mpsc::channel();
+		self.lock().senders.push(sender);
+		receiver
+	}
+
+	/// Resolves once the signal fires.
+	pub fn lost(&self) -> Lost<'_> {
+		Lost { signal: self }
+	}
+
+	pub(crate) fn fire(&self, reason: LostReason) {
+		let (wakers, callbacks) = {
+			let mut state = self.lock();
+			if state.reason.is_some() {
+				return;
+			}
+
+			state.reason = Some(reason);
+			state.senders.retain(|sender| sender.send(reason).is_ok());
+			(std::mem::take(&mut state.wakers), state.callbacks.clone())
+		};
+
+		wakers.into_iter().for_each(Waker::wake);
+		callbacks.iter().for_each(|callback| callback(reason));
+	}
+
+	pub(crate) fn reset(&self) {
+		self.lock().reason = None;
+	}
+
+	fn lock(&self) -> MutexGuard<'_, SignalState> {
+		self.state.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl Debug for LostSignal {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("LostSignal").field("reason", &self.reason()).finish()
+	}
+}
+
+/// Future returned by `LostSignal::lost`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Lost<'a> {
+	signal: &'a LostSignal,
+}
+
+impl Future for Lost<'_> {
+	type Output = LostReason;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let mut state = self.signal.lock();
+		match state.reason {
+			Some(reason) => Poll::Ready(reason),
+			None => {
+				if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+					state.wakers.push(cx.waker().clone());
+				}
+				Poll::Pending
+			}
+		}
+	}
+}
@@ -0,0 +1,268 @@
+//! Metrics and traces of the backend calls made by `DistLock` and `AsyncDistLock`.
+//!
+//! With the `metrics` feature every `acquire`, `extend` and `release` that reaches the driver is
+//! recorded through the `metrics` facade, so any exporter installed by the application picks them
+//! up. All metrics carry the `lock` and `provider` labels, the per call ones also an `op` label of
+//! `acquire`, `extend` or `release`.
+//!
+//! With the `tracing` feature the same calls run inside an `acquire`, `extend` or `release` span
+//! with the `lock`, `provider` and `owner` fields, and `outcome` and `latency_ms` once the call
+//! returns. The release of a lock dropped while held runs inside a `release_on_drop` span, and
+//! the drivers emit debug events from within these spans. Without the features nothing is
+//! recorded.
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::core::LockConfig;
+use crate::core::LockState;
+use crate::error::LockResult;
+
+/// Counter of backend calls.
+pub const ATTEMPTS: &str = "dist_lock_attempts_total";
+/// Counter of calls that got or kept the lock, or released it.
+pub const SUCCESSES: &str = "dist_lock_successes_total";
+/// Counter of acquires that found the lock held by someone else and extends that found it lost.
+pub const CONTENDED: &str = "dist_lock_contended_total";
+/// Counter of calls that failed with an error.
+pub const ERRORS: &str = "dist_lock_errors_total";
+/// Histogram of the round-trip time of a call in seconds.
+pub const LATENCY: &str = "dist_lock_latency_seconds";
+/// Histogram of the time from acquiring a lock to releasing it in seconds.
+pub const HOLD: &str = "dist_lock_hold_seconds";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+	Acquire,
+	Extend,
+	Release,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+	Success,
+	Contended,
+	Error,
+}
+
+impl Outcome {
+	fn of(op: Op, result: &LockResult<LockState>) -> Outcome {
+		match result {
+			Ok(state) if state.is_locked() || op == Op::Release => Outcome::Success,
+			Ok(_) => Outcome::Contended,
+			Err(_) => Outcome::Error,
+		}
+	}
+}
+
+/// A backend call of a `DistLock`, recorded when it finishes.
+pub(crate) struct Call {
+	op: Op,
+	start: Instant,
+	span: Span,
+}
+
+impl Call {
+	pub(crate) fn start(op: Op, config: &LockConfig, provider: &'static str) -> Call {
+		Call { op, start: Instant::now(), span: span(op, config, provider) }
+	}
+
+	/// When the call started, the acquisition time of a lock it got.
+	pub(crate) fn started(&self) -> Instant {
+		self.start
+	}
+
+	pub(crate) fn finish(
+		&self,
+		config: &LockConfig,
+		provider: &'static str,
+		result: &LockResult<LockState>,
+	) {
+		let outcome = Outcome::of(self.op, result);
+		let latency = self.start.elapsed();
+		record(self.op, config, provider, outcome, latency);
+		trace(self.op, &self.span, result, outcome, latency);
+	}
+}
+
+cfg_if::cfg_if! {
+	if #[cfg(any(feature = "metrics", feature = "tracing"))] {
+		impl Op {
+			fn as_str(self) -> &'static str {
+				match self {
+					Op::Acquire => "acquire",
+					Op::Extend => "extend",
+					Op::Release => "release",
+				}
+			}
+		}
+	}
+}
+
+cfg_if::cfg_if! {
+	if #[cfg(feature = "metrics")] {
+		fn record(
+			op: Op,
+			config: &LockConfig,
+			provider: &'static str,
+			outcome: Outcome,
+			latency: Duration,
+		) {
+			let labels = [
+				("lock", config.name().to_owned()),
+				("provider", provider.to_owned()),
+				("op", op.as_str().to_owned()),
+			];
+			metrics::counter!(ATTEMPTS, &labels).increment(1);
+			metrics::histogram!(LATENCY, &labels).record(latency.as_secs_f64());
+			let counter = match outcome {
+				Outcome::Success => SUCCESSES,
+				Outcome::Contended => CONTENDED,
+				Outcome::Error => ERRORS,
+			};
+			metrics::counter!(counter, &labels).increment(1);
+		}
+
+		/// Record how long a lock was held once it is released.
+		pub(crate) fn record_hold(config: &LockConfig, provider: &'static str, held: Duration) {
+			let labels = [("lock", config.name().to_owned()), ("provider", provider.to_owned())];
+			metrics::histogram!(HOLD, &labels).record(held.as_secs_f64());
+		}
+	} else {
+		fn record(
+			_op: Op,
+			_config: &LockConfig,
+			_provider: &'static str,
+			_outcome: Outcome,
+			_latency: Duration,
+		) {
+		}
+
+		pub(crate) fn record_hold(_config: &LockConfig, _provider: &'static str, _held: Duration) {}
+	}
+}
+
+cfg_if::cfg_if! {
+	if #[cfg(feature = "tracing")] {
+		type Span = tracing::Span;
+
+		impl Outcome {
+			fn as_str(self) -> &'static str {
+				match self {
+					Outcome::Success => "success",
+					Outcome::Contended => "contended",
+					Outcome::Error => "error",
+				}
+			}
+		}
+
+		impl Call {
+			pub(crate) fn span(&self) -> &Span {
+				&self.span
+			}
+		}
+
+		macro_rules! lock_span {
+			($name: literal, $config: expr, $provider: expr) => {
+				tracing::info_span!(
+					$name,
+					lock = %$config.name(),
+					provider = $provider,
+					owner = %$config.owner(),
+					outcome = tracing::field::Empty,
+					latency_ms = tracing::field::Empty,
+				)
+			};
+		}
+
+		fn span(op: Op, config: &LockConfig, provider: &'static str) -> Span {
+			match op {
+				Op::Acquire => lock_span!("acquire", config, provider),
+				Op::Extend => lock_span!("extend", config, provider),
+				Op::Release => lock_span!("release", config, provider),
+			}
+		}
+
+		fn trace(
+			op: Op,
+			span: &Span,
+			result: &LockResult<LockState>,
+			outcome: Outcome,
+			latency: Duration,
+		) {
+			span.record("outcome", outcome.as_str());
+			span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+			match result {
+				Err(e) => tracing::warn!(parent: span, error = %e, "{} failed", op.as_str()),
+				Ok(state) => tracing::debug!(
+					parent: span,
+					token = state.fencing_token(),
+					holds = state.holds(),
+					"{} {}",
+					op.as_str(),
+					outcome.as_str(),
+				),
+			}
+		}
+
+		/// Run the release of a lock dropped while held inside a `release_on_drop` span.
+		pub(crate) fn on_drop<R>(config: &LockConfig, provider: &'static str, f: impl FnOnce() -> R) -> R {
+			let span = tracing::info_span!(
+				"release_on_drop",
+				lock = %config.name(),
+				provider = provider,
+				owner = %config.owner(),
+			);
+			span.in_scope(f)
+		}
+
+		/// Run a driver call inside the span of a `Call`, awaiting it for async drivers.
+		macro_rules! instrument {
+			($call: expr, $future: expr, .await) => {
+				tracing::Instrument::instrument($future, $call.span().clone()).await
+			};
+			($call: expr, $result: expr $(,)?) => {
+				$call.span().in_scope(|| $result)
+			};
+		}
+
+		/// A debug event of a driver, inside the span of the running call.
+		macro_rules! event {
+			($($arg: tt)*) => {
+				tracing::debug!($($arg)*)
+			};
+		}
+	} else {
+		type Span = ();
+
+		fn span(_op: Op, _config: &LockConfig, _provider: &'static str) -> Span {}
+
+		fn trace(
+			_op: Op,
+			_span: &Span,
+			_result: &LockResult<LockState>,
+			_outcome: Outcome,
+			_latency: Duration,
+		) {
+		}
+
+		pub(crate) fn on_drop<R>(_config: &LockConfig, _provider: &'static str, f: impl FnOnce() -> R) -> R {
+			f()
+		}
+
+		macro_rules! instrument {
+			($call: expr, $future: expr, .await) => {
+				$future.await
+			};
+			($call: expr, $result: expr $(,)?) => {
+				$result
+			};
+		}
+
+		macro_rules! event {
+			($($arg: tt)*) => {};
+		}
+	}
+}
+
+pub(crate) use event;
+pub(crate) use instrument;
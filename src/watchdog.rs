@@ -0,0 +1,213 @@
+//! Background lease renewal for long running jobs.
+//!
+//! A `Watchdog` (or `AsyncWatchdog`) calls `extend` every `interval` until the lock is released,
+//! the lock is dropped or the watchdog is stopped. The first renewal failure stops it, the error
+//! is returned from `stop` and fires the lock's `LostSignal`.
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::thread;
+use std::thread::JoinHandle;
+
+use chrono::Duration;
+
+use crate::core::DistLock;
+use crate::core::LockConfig;
+use crate::core::Lockable;
+use crate::error::LockError;
+use crate::error::LockResult;
+use crate::signal::LostReason;
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub use self::task::AsyncWatchdog;
+
+/// Default renewal interval: a third of `max_lock`.
+fn default_interval(config: &LockConfig) -> Duration {
+	*config.max_lock() / 3
+}
+
+/// Renews a `DistLock` from a std thread.
+#[derive(Debug)]
+pub struct Watchdog {
+	stop: Option<Sender<()>>,
+	handle: Option<JoinHandle<LockResult<()>>>,
+}
+
+impl Watchdog {
+	pub fn spawn<T>(lock: &Arc<DistLock<T>>) -> Watchdog
+	where
+		T: Lockable + Send + Sync + 'static,
+	{
+		Self::spawn_every(lock, default_interval(lock.config()))
+	}
+
+	pub fn spawn_every<T>(lock: &Arc<DistLock<T>>, interval: Duration) -> Watchdog
+	where
+		T: Lockable + Send + Sync + 'static,
+	{
+		let (stop, stopped) = mpsc::channel();
+		let lock = Arc::downgrade(lock);
+		let interval = interval.to_std().unwrap_or_default();
+		let handle = thread::spawn(move || loop {
+			match stopped.recv_timeout(interval) {
+				Err(RecvTimeoutError::Timeout) => {}
+				_ => return Ok(()),
+			}
+
+			let Some(lock) = Weak::upgrade(&lock) else {
+				return Ok(());
+			};
+
+			if !lock.state().is_locked() {
+				return Ok(());
+			}
+
+			match lock.extend() {
+				Ok(true) => {}
+				Ok(false) => return Err(LockError::LockReleased),
+				Err(e) => {
+					lock.lost_signal().fire(LostReason::RenewalFailed);
+					return Err(e);
+				}
+			}
+		});
+
+		Watchdog { stop: Some(stop), handle: Some(handle) }
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.handle.as_ref().is_some_and(|handle| !handle.is_finished())
+	}
+
+	/// Stop renewing and return the renewal failure if there was one.
+	pub fn stop(mut self) -> LockResult<()> {
+		self.stop.take();
+		let handle = self.handle.take().expect("watchdog handle taken twice");
+		handle.join().unwrap_or(Ok(()))
+	}
+}
+
+impl Drop for Watchdog {
+	fn drop(&mut self) {
+		self.stop.take();
+	}
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+mod task {
+	use std::sync::atomic::AtomicBool;
+	use std::sync::atomic::Ordering;
+	use std::sync::Arc;
+	use std::sync::Weak;
+
+	use chrono::Duration;
+
+	use super::default_interval;
+	use crate::core::AsyncDistLock;
+	use crate::core::AsyncLockable;
+	use crate::error::LockError;
+	use crate::error::LockResult;
+	use crate::retry::sleep_async;
+	use crate::signal::LostReason;
+
+	#[cfg(feature = "tokio")]
+	type JoinHandle = tokio::task::JoinHandle<LockResult<()>>;
+	#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+	type JoinHandle = async_std::task::JoinHandle<LockResult<()>>;
+
+	/// Renews an `AsyncDistLock` from a tokio or async-std task.
+	#[derive(Debug)]
+	pub struct AsyncWatchdog {
+		stopped: Arc<AtomicBool>,
+		handle: Option<JoinHandle>,
+	}
+
+	impl AsyncWatchdog {
+		pub fn spawn<T>(lock: &Arc<AsyncDistLock<T>>) -> AsyncWatchdog
+		where
+			T: AsyncLockable + Send + Sync + 'static,
+		{
+			Self::spawn_every(lock, default_interval(lock.config()))
+		}
+
+		pub fn spawn_every<T>(lock: &Arc<AsyncDistLock<T>>, interval: Duration) -> AsyncWatchdog
+		where
+			T: AsyncLockable + Send + Sync + 'static,
+		{
+			let stopped = Arc::new(AtomicBool::new(false));
+			let task = renew(Arc::downgrade(lock), interval, stopped.clone());
+
+			#[cfg(feature = "tokio")]
+			let handle = tokio::spawn(task);
+			#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+			let handle = async_std::task::spawn(task);
+
+			AsyncWatchdog { stopped, handle: Some(handle) }
+		}
+
+		pub fn is_running(&self) -> bool {
+			!self.stopped.load(Ordering::Acquire)
+		}
+
+		/// Stop renewing and return the renewal failure if there was one.
+		pub async fn stop(mut self) -> LockResult<()> {
+			self.stopped.store(true, Ordering::Release);
+			let handle = self.handle.take().expect("watchdog handle taken twice");
+
+			#[cfg(feature = "tokio")]
+			{
+				handle.abort();
+				handle.await.unwrap_or(Ok(()))
+			}
+
+			#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+			handle.cancel().await.unwrap_or(Ok(()))
+		}
+	}
+
+	impl Drop for AsyncWatchdog {
+		fn drop(&mut self) {
+			self.stopped.store(true, Ordering::Release);
+
+			#[cfg(feature = "tokio")]
+			if let Some(handle) = &self.handle {
+				handle.abort();
+			}
+		}
+	}
+
+	async fn renew<T: AsyncLockable>(
+		lock: Weak<AsyncDistLock<T>>,
+		interval: Duration,
+		stopped: Arc<AtomicBool>,
+	) -> LockResult<()> {
+		let result = loop {
+			sleep_async(interval).await;
+			if stopped.load(Ordering::Acquire) {
+				break Ok(());
+			}
+
+			let Some(lock) = lock.upgrade() else {
+				break Ok(());
+			};
+
+			if !lock.state().is_locked() {
+				break Ok(());
+			}
+
+			match lock.extend().await {
+				Ok(true) => {}
+				Ok(false) => break Err(LockError::LockReleased),
+				Err(e) => {
+					lock.lost_signal().fire(LostReason::RenewalFailed);
+					break Err(e);
+				}
+			}
+		};
+
+		stopped.store(true, Ordering::Release);
+		result
+	}
+}
@@ -0,0 +1,70 @@
+use chrono::Duration;
+use dist_lock::core::LockConfig;
+use dist_lock::core::LockState;
+use dist_lock::error::ConfigError;
+use dist_lock::error::LockResult;
+use dist_lock::retry::RetryPolicy;
+
+#[test]
+fn test_builder() -> LockResult<()> {
+	let config = LockConfig::builder("job")
+		.namespace("billing")
+		.owner("worker-1")
+		.min_lock(Duration::seconds(1))
+		.max_lock(Duration::seconds(10))
+		.drift(Duration::milliseconds(100))
+		.retry_policy(RetryPolicy::fixed(Duration::milliseconds(50)).max_attempts(3))
+		.build()?;
+
+	assert_eq!(config.name(), "billing:job");
+	assert_eq!(config.namespace(), Some("billing"));
+	assert_eq!(config.owner().id(), "worker-1");
+	assert_eq!(config.drift(), &Duration::milliseconds(100));
+	Ok(())
+}
+
+#[test]
+fn test_unchecked_drift() {
+	// with_drift skips the builder check, a drift above max_lock must not overflow
+	let config =
+		LockConfig::from_mills("job".to_string(), 0, 1_000).with_drift(Duration::seconds(2));
+	let state = LockState::new(true, chrono::DateTime::<chrono::Utc>::MIN_UTC);
+	assert!(!state.check_locked(&config));
+	assert!(!LockState::new(true, config.now()).check_locked(&config));
+}
+
+#[test]
+fn test_builder_invalid() {
+	let builder = LockConfig::builder("job").max_lock(Duration::seconds(10));
+	let error = |builder: dist_lock::core::LockConfigBuilder| builder.build().unwrap_err();
+
+	assert_eq!(
+		error(LockConfig::builder("job")),
+		ConfigError::NonPositiveMaxLock(Duration::zero())
+	);
+	assert_eq!(
+		error(LockConfig::builder("").max_lock(Duration::seconds(10))),
+		ConfigError::EmptyName
+	);
+	assert_eq!(
+		error(builder.clone().min_lock(Duration::seconds(-1))),
+		ConfigError::NegativeMinLock(Duration::seconds(-1))
+	);
+	assert_eq!(
+		error(builder.clone().min_lock(Duration::seconds(11))),
+		ConfigError::MinLockExceedsMaxLock {
+			min_lock: Duration::seconds(11),
+			max_lock: Duration::seconds(10)
+		}
+	);
+	assert_eq!(
+		error(builder.clone().drift(Duration::seconds(10))),
+		ConfigError::InvalidDrift(Duration::seconds(10))
+	);
+	assert_eq!(error(builder.clone().owner("")), ConfigError::EmptyOwner);
+	assert_eq!(error(builder.clone().namespace("")), ConfigError::EmptyNamespace);
+	assert_eq!(
+		error(builder.fair(Duration::zero())),
+		ConfigError::NonPositiveQueueTimeout(Duration::zero())
+	);
+}
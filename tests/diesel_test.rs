@@ -8,8 +8,33 @@
 //     lock_until BIGINT NOT NULL,
 //     locked_at BIGINT NOT NULL,
 //     locked_by VARCHAR(255) NOT NULL,
+//     version BIGINT NOT NULL DEFAULT 0,
+//     holds INT NOT NULL DEFAULT 0,
 //     PRIMARY KEY (name)
 // );
+// CREATE TABLE t_dist_rw_lock(
+//     name VARCHAR(64) NOT NULL,
+//     mode VARCHAR(8) NOT NULL,
+//     locked_by VARCHAR(255) NOT NULL,
+//     lock_until BIGINT NOT NULL,
+//     locked_at BIGINT NOT NULL,
+//     PRIMARY KEY (name, mode, locked_by)
+// );
+// CREATE TABLE t_dist_semaphore(
+//     name VARCHAR(64) NOT NULL,
+//     locked_by VARCHAR(255) NOT NULL,
+//     lock_until BIGINT NOT NULL,
+//     locked_at BIGINT NOT NULL,
+//     PRIMARY KEY (name, locked_by)
+// );
+// CREATE TABLE t_dist_lock_waiter(
+//     id BIGINT NOT NULL AUTO_INCREMENT, -- BIGSERIAL on postgres
+//     name VARCHAR(64) NOT NULL,
+//     locked_by VARCHAR(255) NOT NULL,
+//     lock_until BIGINT NOT NULL,
+//     PRIMARY KEY (id),
+//     UNIQUE (name, locked_by)
+// );
 
 #[cfg(feature = "diesel")]
 mod diesel {
@@ -17,6 +42,7 @@ mod diesel {
 	use diesel::Connection;
 	use dist_lock::core::DistLock;
 	use dist_lock::core::LockConfig;
+	use dist_lock::core::LockOwner;
 	use dist_lock::core::Lockable;
 	use dist_lock::error::LockResult;
 	use dist_lock::provider::diesel::DieselDriver;
@@ -94,6 +120,8 @@ mod diesel {
 			lock_until BIGINT NOT NULL,
 			locked_at BIGINT NOT NULL,
 			locked_by VARCHAR(255) NOT NULL,
+			version BIGINT NOT NULL DEFAULT 0,
+			holds INT NOT NULL DEFAULT 0,
 			PRIMARY KEY (name)
 		);",
 		)?;
@@ -120,6 +148,8 @@ mod diesel {
 			lock_until BIGINT NOT NULL,
 			locked_at BIGINT NOT NULL,
 			locked_by VARCHAR(255) NOT NULL,
+			version BIGINT NOT NULL DEFAULT 0,
+			holds INT NOT NULL DEFAULT 0,
 			PRIMARY KEY (name)
 		);",
 		)?;
@@ -131,6 +161,649 @@ mod diesel {
 		check_lock(&dist_lock)
 	}
 
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_owner() -> LockResult<()> {
+		let lock_name = "owner_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		let holder =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let other = DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		assert!(holder.acquire()?);
+		let token = holder.state().fencing_token();
+		assert!(token > 0);
+		assert!(!other.acquire()?);
+		assert!(!other.driver().extend_lock(other.config())?.is_locked());
+		other.driver().release_lock(other.config(), &holder.state())?;
+		assert!(holder.extend()?);
+		assert_eq!(holder.state().fencing_token(), token);
+		holder.release()?;
+		assert!(other.acquire()?);
+		assert!(other.state().fencing_token() > token);
+		other.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_retry() -> LockResult<()> {
+		use dist_lock::error::LockError;
+		use dist_lock::retry::RetryPolicy;
+		let lock_name = "retry_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		let holder =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let other = DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		let guard = holder.try_lock()?.expect("lock is free");
+		let result = other.acquire_timeout(Duration::milliseconds(300));
+		assert!(matches!(result, Err(LockError::Timeout)));
+		let policy =
+			RetryPolicy::exponential(Duration::milliseconds(10), Duration::milliseconds(50));
+		let result = other.acquire_with(policy.max_attempts(3));
+		assert!(matches!(result, Err(LockError::LockFailed)));
+		drop(guard);
+		other.acquire_timeout(Duration::milliseconds(300))?;
+		other.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_watchdog() -> LockResult<()> {
+		use dist_lock::watchdog::Watchdog;
+		use std::sync::Arc;
+		let lock_name = "watchdog_lock".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(1));
+		let holder =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let holder = Arc::new(holder);
+		let other = DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		assert!(holder.acquire()?);
+		let watchdog = Watchdog::spawn_every(&holder, Duration::milliseconds(300));
+		thread::sleep(core::time::Duration::from_millis(1500));
+		assert!(watchdog.is_running());
+		assert!(!other.acquire()?);
+		watchdog.stop()?;
+		holder.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_lost_signal() -> LockResult<()> {
+		use dist_lock::signal::LostReason;
+		let lock_name = "lost_lock".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(1));
+		let holder =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let other = DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let receiver = holder.lost_signal().subscribe();
+
+		assert!(holder.acquire()?);
+		thread::sleep(core::time::Duration::from_millis(1200));
+		assert!(other.acquire()?);
+		assert!(!holder.extend()?);
+		assert_eq!(receiver.try_recv().ok(), Some(LostReason::Expired));
+		assert!(holder.lost_signal().is_lost());
+
+		other.release()?;
+		assert!(holder.acquire()?);
+		assert!(!holder.lost_signal().is_lost());
+		holder.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_reentrant() -> LockResult<()> {
+		let lock_name = "reentrant_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10))
+				.with_reentrant(true);
+		let first =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let second =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let stranger = DistLock::new(
+			config.with_owner(LockOwner::new()),
+			DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?),
+		);
+
+		assert!(first.acquire()?);
+		let token = first.state().fencing_token();
+		assert!(first.acquire()?);
+		assert!(second.acquire()?);
+		assert_eq!(second.state().holds(), 3);
+		assert_eq!(second.state().fencing_token(), token);
+		assert!(!stranger.acquire()?);
+
+		first.release()?;
+		assert_eq!(first.state().holds(), 2);
+		second.release()?;
+		assert!(!stranger.acquire()?);
+		first.release()?;
+		assert!(!first.state().is_locked());
+		assert!(stranger.acquire()?);
+		stranger.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_rw_lock() -> LockResult<()> {
+		use dist_lock::rwlock::DistRwLock;
+		let lock_name = "rw_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		let rw_lock = |config: &LockConfig| -> LockResult<_> {
+			let driver = DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?);
+			Ok(DistRwLock::new(config.clone(), driver))
+		};
+		let reader = rw_lock(&config)?;
+		let other_reader = rw_lock(&config)?;
+		let writer = rw_lock(&config)?;
+
+		let read = reader.read()?.expect("no writer");
+		let other_read = other_reader.read()?.expect("readers share the lock");
+		assert!(writer.write()?.is_none());
+		drop(read);
+		assert!(writer.write()?.is_none());
+		drop(other_read);
+
+		let write = writer.write()?.expect("readers are gone");
+		assert!(reader.read()?.is_none());
+		assert!(write.extend()?);
+		write.release()?;
+		let _read = reader.read()?.expect("writer is gone");
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_semaphore() -> LockResult<()> {
+		use dist_lock::semaphore::DistSemaphore;
+		let lock_name = "semaphore".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(1));
+		let semaphore = |config: &LockConfig| -> LockResult<_> {
+			let driver = DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?);
+			Ok(DistSemaphore::new(config.clone(), 2, driver))
+		};
+		let first = semaphore(&config)?;
+		let second = semaphore(&config)?;
+		let third = semaphore(&config)?;
+
+		let permit = first.try_permit()?.expect("2 permits left");
+		assert!(second.acquire()?);
+		assert!(third.try_permit()?.is_none());
+		drop(permit);
+		let permit = third.try_permit()?.expect("a permit was given back");
+		assert!(permit.extend()?);
+
+		// the permit of second is never released and expires, third keeps renewing
+		std::mem::forget(second);
+		thread::sleep(core::time::Duration::from_millis(600));
+		assert!(permit.extend()?);
+		thread::sleep(core::time::Duration::from_millis(600));
+		assert!(first.acquire()?);
+		assert!(permit.extend()?);
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_multi_lock() -> LockResult<()> {
+		use dist_lock::multilock::MultiLock;
+		let config = |name: &str| {
+			LockConfig::new(name.to_string(), Duration::seconds(0), Duration::seconds(10))
+		};
+		let dist_lock = |name: &str| -> LockResult<_> {
+			let driver = DieselDriver::new(&name.to_string(), Some("t"), sqlite_conn()?);
+			Ok(DistLock::new(config(name), driver))
+		};
+		let multi = MultiLock::new(
+			config("multi_lock"),
+			["account_2", "account_1", "account_2"],
+			DieselDriver::new(&"multi_lock".to_string(), Some("t"), sqlite_conn()?),
+		);
+		let names = multi.configs().iter().map(|config| config.name().as_str()).collect::<Vec<_>>();
+		assert_eq!(names, ["account_1", "account_2"]);
+
+		let single = dist_lock("account_2")?;
+		assert!(single.acquire()?);
+		assert!(multi.try_lock()?.is_none());
+		let rolled_back = dist_lock("account_1")?;
+		assert!(rolled_back.acquire()?);
+		rolled_back.release()?;
+		single.release()?;
+
+		let guard = multi.try_lock()?.expect("names are free");
+		assert!(guard.fencing_tokens().iter().all(|token| *token > 0));
+		assert!(!single.acquire()?);
+		assert!(guard.extend()?);
+		drop(guard);
+		assert!(single.acquire()?);
+		single.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_fair() -> LockResult<()> {
+		use dist_lock::error::LockError;
+		let lock_name = "fair_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10))
+				.with_fair(Duration::seconds(5));
+		let lock = || -> LockResult<_> {
+			Ok(DistLock::new(
+				config.clone(),
+				DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?),
+			))
+		};
+		let holder = lock()?;
+		let first = lock()?;
+		let second = lock()?;
+		let quitter = lock()?;
+
+		assert!(holder.acquire()?);
+		let result = quitter.acquire_timeout(Duration::milliseconds(200));
+		assert!(matches!(result, Err(LockError::Timeout)));
+		assert!(!first.acquire()?);
+		assert!(!second.acquire()?);
+		holder.release()?;
+
+		// `quitter` left the queue, the lock is free but `first` is ahead of `second`
+		assert!(!second.acquire()?);
+		assert!(first.acquire()?);
+		first.release()?;
+		assert!(second.acquire()?);
+		second.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_drift() -> LockResult<()> {
+		let lock_name = "drift_lock".to_string();
+		let config = LockConfig::builder(lock_name.clone())
+			.max_lock(Duration::seconds(1))
+			.drift(Duration::milliseconds(500))
+			.backend_time(true)
+			.build()?;
+		let holder =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let other = DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		assert!(holder.acquire()?);
+		thread::sleep(core::time::Duration::from_millis(600));
+		// the holder stops trusting the lock a drift before it expires
+		assert!(!holder.state().check_locked(holder.config()));
+		thread::sleep(core::time::Duration::from_millis(600));
+		// expired, but not for longer than the drift allowance
+		assert!(!other.acquire()?);
+		thread::sleep(core::time::Duration::from_millis(400));
+		assert!(other.acquire()?);
+		other.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_namespace() -> LockResult<()> {
+		let lock_name = "namespace_lock".to_string();
+		let config = LockConfig::builder(lock_name.clone())
+			.namespace("diesel")
+			.max_lock(Duration::seconds(10))
+			.build()?;
+		let driver = DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?);
+		let first = DistLock::new(config.clone(), driver);
+		let second =
+			DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		assert!(first.acquire()?);
+		let info = first.driver().inspect(first.config())?.expect("lock is held");
+		assert_eq!(info.name(), "diesel:namespace_lock");
+		assert!(!second.acquire()?);
+		first.release()?;
+		assert!(second.acquire()?);
+		second.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_leader_election() -> LockResult<()> {
+		use dist_lock::election::LeaderElector;
+		use dist_lock::election::LeaderEvent;
+		use dist_lock::retry::RetryPolicy;
+		let lock_name = "leader_lock".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(1));
+		let elector = || -> LockResult<_> {
+			let driver = DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?);
+			Ok(LeaderElector::new(config.clone(), driver))
+		};
+		let first = elector()?;
+		let second = elector()?;
+		let events = first.subscribe();
+
+		assert!(first.try_campaign()?);
+		let term = first.term().expect("first leads");
+		assert!(!second.try_campaign()?);
+		assert_eq!(second.term(), None);
+		assert_eq!(second.leader()?.as_ref(), Some(first.id()));
+
+		// the watchdog keeps the lease past `max_lock`
+		thread::sleep(core::time::Duration::from_millis(1500));
+		assert!(first.is_leader());
+		assert!(!second.try_campaign()?);
+
+		first.resign()?;
+		assert!(!first.is_leader());
+		assert_eq!(
+			events.try_iter().collect::<Vec<_>>(),
+			[LeaderEvent::Elected(term), LeaderEvent::Resigned]
+		);
+
+		second.campaign_with(RetryPolicy::default().timeout(Duration::milliseconds(500)))?;
+		assert!(second.term().is_some_and(|second| second > term));
+		assert_eq!(first.leader()?.as_ref(), Some(second.id()));
+		second.resign()?;
+		assert_eq!(first.leader()?, None);
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_quorum() -> LockResult<()> {
+		use diesel::connection::SimpleConnection;
+		use dist_lock::provider::QuorumDriver;
+		let lock_name = "quorum_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		// one table per prefix stands in for an independent backend
+		let driver = |prefix: &str| -> LockResult<_> {
+			let mut conn = sqlite_conn()?;
+			conn.batch_execute(&sqlite_schema(prefix))?;
+			Ok(DieselDriver::new(&lock_name, Some(prefix), conn))
+		};
+		let quorum = |config: &LockConfig| -> LockResult<_> {
+			let drivers = vec![driver("q1")?, driver("q2")?, driver("q3")?];
+			Ok(DistLock::new(config.clone(), QuorumDriver::new(drivers)))
+		};
+		let holder = quorum(&config)?;
+		let other = quorum(&config)?;
+
+		assert!(holder.acquire()?);
+		assert!(holder.state().lock_time() < chrono::Utc::now());
+		assert!(!other.acquire()?);
+		assert!(holder.extend()?);
+		holder.release()?;
+
+		// a majority held elsewhere fails the quorum and releases the minority
+		let q1 = DistLock::new(config.clone(), driver("q1")?);
+		let q2 = DistLock::new(config.clone(), driver("q2")?);
+		assert!(q1.acquire()? && q2.acquire()?);
+		assert!(!other.acquire()?);
+		let q3 = DistLock::new(config.clone(), driver("q3")?);
+		assert!(q3.acquire()?);
+		q3.release()?;
+
+		// a single backend held elsewhere leaves a majority
+		q2.release()?;
+		assert!(other.acquire()?);
+		other.release()?;
+		q1.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_inspect() -> LockResult<()> {
+		let lock_name = "inspect_lock".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(5))
+			.with_owner(LockOwner::from_id("inspected"));
+		let dist_lock =
+			DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		assert!(dist_lock.driver().inspect(dist_lock.config())?.is_none());
+		assert!(dist_lock.acquire()?);
+		let info = dist_lock.driver().inspect(dist_lock.config())?.expect("lock is held");
+		assert_eq!(info.name(), lock_name);
+		assert_eq!(info.holder().id(), "inspected");
+		assert_eq!(info.fencing_token(), Some(dist_lock.state().fencing_token()));
+		assert_eq!(info.lock_until() - info.locked_at(), Duration::seconds(5));
+		assert_eq!(info.holds(), 1);
+
+		dist_lock.release()?;
+		assert!(dist_lock.driver().inspect(dist_lock.config())?.is_none());
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_mock_clock() -> LockResult<()> {
+		use chrono::Utc;
+		use dist_lock::clock::MockClock;
+		let clock = MockClock::new(Utc::now());
+		let lock_name = "mock_clock_lock".to_string();
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(30), Duration::seconds(60))
+				.with_clock(clock.clone());
+		let first =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let second =
+			DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+
+		assert!(first.acquire()?);
+		clock.advance(Duration::seconds(50));
+		assert!(first.extend()?);
+		clock.advance(Duration::seconds(50));
+		// extended, so still held after the first max_lock
+		assert!(first.state().check_locked(first.config()));
+		assert!(!second.acquire()?);
+		clock.advance(Duration::seconds(20));
+		assert!(!first.state().check_locked(first.config()));
+		assert!(second.acquire()?);
+
+		// kept until min_lock has passed
+		second.release()?;
+		assert!(!first.acquire()?);
+		clock.advance(Duration::seconds(30));
+		assert!(first.acquire()?);
+		first.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "diesel_sqlite")]
+	#[test]
+	fn test_sqlite_admin() -> LockResult<()> {
+		use dist_lock::admin::LockAdmin;
+		let names = ["admin_lock_a".to_string(), "admin_lock_b".to_string()];
+		let locks = names
+			.iter()
+			.map(|name| -> LockResult<_> {
+				let config =
+					LockConfig::new(name.clone(), Duration::seconds(0), Duration::seconds(5));
+				Ok(DistLock::new(config, DieselDriver::new(name, Some("t"), sqlite_conn()?)))
+			})
+			.collect::<LockResult<Vec<_>>>()?;
+		let admin = DieselDriver::new(&"admin".to_string(), Some("t"), sqlite_conn()?);
+
+		locks.iter().try_for_each(|lock| lock.acquire().map(|acquired| assert!(acquired)))?;
+		let listed = admin.list_locks()?;
+		for lock in &locks {
+			let info = listed.iter().find(|info| info.name() == lock.config().name()).unwrap();
+			assert_eq!(info.holder(), lock.config().owner());
+			assert_eq!(info.fencing_token(), Some(lock.state().fencing_token()));
+		}
+
+		assert!(admin.force_unlock(&names[0])?);
+		assert!(!admin.force_unlock(&names[0])?);
+		assert!(!admin.list_locks()?.iter().any(|info| info.name() == names[0]));
+		// the holder finds out on its next extension
+		assert!(!locks[0].extend()?);
+		assert!(locks[1].extend()?);
+		locks[1].release()?;
+		Ok(())
+	}
+
+	#[cfg(all(feature = "diesel_sqlite", feature = "metrics"))]
+	#[test]
+	fn test_sqlite_metrics() -> LockResult<()> {
+		use dist_lock::telemetry;
+		use metrics_util::debugging::DebugValue;
+		use metrics_util::debugging::DebuggingRecorder;
+		let lock_name = "metrics_lock".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(5));
+		let holder =
+			DistLock::new(config.clone(), DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let other = DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+		let recorder = DebuggingRecorder::new();
+		let snapshotter = recorder.snapshotter();
+
+		metrics::with_local_recorder(&recorder, || -> LockResult<()> {
+			assert!(holder.acquire()?);
+			assert!(!other.acquire()?);
+			assert!(holder.extend()?);
+			holder.release()
+		})?;
+
+		let metrics = snapshotter.snapshot().into_vec();
+		let count = |name: &str, op: &str| {
+			let found = metrics.iter().find_map(|(key, _, _, value)| {
+				let key = key.key();
+				let labelled = key.labels().any(|label| label.key() == "op" && label.value() == op);
+				match value {
+					DebugValue::Counter(count) if key.name() == name && labelled => Some(*count),
+					_ => None,
+				}
+			});
+			found.unwrap_or_default()
+		};
+		assert_eq!(count(telemetry::ATTEMPTS, "acquire"), 2);
+		assert_eq!(count(telemetry::SUCCESSES, "acquire"), 1);
+		assert_eq!(count(telemetry::CONTENDED, "acquire"), 1);
+		assert_eq!(count(telemetry::SUCCESSES, "extend"), 1);
+		assert_eq!(count(telemetry::SUCCESSES, "release"), 1);
+		assert_eq!(count(telemetry::ERRORS, "acquire"), 0);
+		assert!(metrics.iter().any(|(key, ..)| key.key().name() == telemetry::HOLD));
+		Ok(())
+	}
+
+	#[cfg(all(feature = "diesel_sqlite", feature = "tracing"))]
+	#[test]
+	fn test_sqlite_tracing() -> LockResult<()> {
+		use std::sync::Arc;
+		use std::sync::Mutex;
+		use tracing::span::Attributes;
+		use tracing::span::Id;
+		use tracing::span::Record;
+		use tracing::Event;
+		use tracing::Metadata;
+		use tracing::Subscriber;
+
+		/// Collects the names of the spans opened.
+		struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+		impl Subscriber for Spans {
+			fn enabled(&self, _: &Metadata<'_>) -> bool {
+				true
+			}
+
+			fn new_span(&self, span: &Attributes<'_>) -> Id {
+				let mut names = self.0.lock().unwrap();
+				names.push(span.metadata().name());
+				Id::from_u64(names.len() as u64)
+			}
+
+			fn record(&self, _: &Id, _: &Record<'_>) {}
+
+			fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+			fn event(&self, _: &Event<'_>) {}
+
+			fn enter(&self, _: &Id) {}
+
+			fn exit(&self, _: &Id) {}
+		}
+
+		let lock_name = "tracing_lock".to_string();
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(5));
+		let names = Arc::new(Mutex::new(vec![]));
+		tracing::subscriber::with_default(Spans(names.clone()), || -> LockResult<()> {
+			let holder = DistLock::new(
+				config.clone(),
+				DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?),
+			);
+			let other =
+				DistLock::new(config, DieselDriver::new(&lock_name, Some("t"), sqlite_conn()?));
+			assert!(holder.acquire()?);
+			assert!(!other.acquire()?);
+			assert!(holder.extend()?);
+			drop(holder);
+			Ok(())
+		})?;
+
+		let names = names.lock().unwrap();
+		assert_eq!(*names, ["acquire", "acquire", "extend", "release_on_drop", "release"]);
+		Ok(())
+	}
+
+	/// Connection to the test database with every table of the `t` prefix in place.
+	#[cfg(feature = "diesel_sqlite")]
+	fn sqlite_conn() -> LockResult<diesel::SqliteConnection> {
+		use diesel::connection::SimpleConnection;
+		let mut conn = diesel::SqliteConnection::establish("diesel_test.db")?;
+		// connections of the same test contend for the file
+		conn.batch_execute("PRAGMA busy_timeout = 5000;")?;
+		conn.batch_execute(&sqlite_schema("t"))?;
+		Ok(conn)
+	}
+
+	/// DDL of the tables `DieselDriver` uses under `prefix`, the sqlite flavor of the schema in
+	/// the README.
+	#[cfg(feature = "diesel_sqlite")]
+	fn sqlite_schema(prefix: &str) -> String {
+		format!(
+			r"CREATE TABLE IF NOT EXISTS {prefix}_dist_lock(
+			name VARCHAR(64) NOT NULL,
+			lock_until BIGINT NOT NULL,
+			locked_at BIGINT NOT NULL,
+			locked_by VARCHAR(255) NOT NULL,
+			version BIGINT NOT NULL DEFAULT 0,
+			holds INT NOT NULL DEFAULT 0,
+			PRIMARY KEY (name)
+		);
+		CREATE TABLE IF NOT EXISTS {prefix}_dist_rw_lock(
+			name VARCHAR(64) NOT NULL,
+			mode VARCHAR(8) NOT NULL,
+			locked_by VARCHAR(255) NOT NULL,
+			lock_until BIGINT NOT NULL,
+			locked_at BIGINT NOT NULL,
+			PRIMARY KEY (name, mode, locked_by)
+		);
+		CREATE TABLE IF NOT EXISTS {prefix}_dist_semaphore(
+			name VARCHAR(64) NOT NULL,
+			locked_by VARCHAR(255) NOT NULL,
+			lock_until BIGINT NOT NULL,
+			locked_at BIGINT NOT NULL,
+			PRIMARY KEY (name, locked_by)
+		);
+		CREATE TABLE IF NOT EXISTS {prefix}_dist_lock_waiter(
+			id INTEGER PRIMARY KEY AUTOINCREMENT,
+			name VARCHAR(64) NOT NULL,
+			locked_by VARCHAR(255) NOT NULL,
+			lock_until BIGINT NOT NULL,
+			UNIQUE (name, locked_by)
+		);"
+		)
+	}
+
 	fn check_lock<T: Lockable>(dist_lock: &DistLock<T>) -> LockResult<()> {
 		let now = Instant::now();
 		assert!(dist_lock.acquire()?);
@@ -0,0 +1,148 @@
+// Start a docker image: docker run -d --name my-etcd -p 2379:2379 quay.io/coreos/etcd
+// etcd --listen-client-urls http://0.0.0.0:2379 --advertise-client-urls http://127.0.0.1:2379
+// Run test: cargo test --no-default-features --features etcd --test etcd_test
+#[cfg(feature = "etcd")]
+mod etcd {
+	use chrono::Duration;
+	use dist_lock::admin::AsyncLockAdmin;
+	use dist_lock::core::AsyncDistLock;
+	use dist_lock::core::AsyncLockable;
+	use dist_lock::core::LockConfig;
+	use dist_lock::core::LockOwner;
+	use dist_lock::error::LockError;
+	use dist_lock::error::LockResult;
+	use dist_lock::provider::EtcdDriver;
+	use etcd_client::Client;
+
+	async fn client() -> LockResult<Client> {
+		Ok(Client::connect(["127.0.0.1:2379"], None).await?)
+	}
+
+	fn config(name: &str, min_lock: i64, max_lock: i64) -> LockConfig {
+		LockConfig::new(name.to_string(), Duration::seconds(min_lock), Duration::seconds(max_lock))
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn test_etcd_lock() -> LockResult<()> {
+		let client = client().await?;
+		let first =
+			AsyncDistLock::new(config("etcd_lock", 0, 10), EtcdDriver::new("etcd_lock", &client));
+		let second =
+			AsyncDistLock::new(config("etcd_lock", 0, 10), EtcdDriver::new("etcd_lock", &client));
+
+		assert!(first.acquire().await?);
+		assert!(!second.acquire().await?);
+		assert!(first.extend().await?);
+		assert!(matches!(second.extend().await, Err(LockError::NotOwner(_))));
+		let token = first.state().fencing_token();
+		first.release().await?;
+		assert!(second.acquire().await?);
+		assert!(second.state().fencing_token() > token);
+
+		let info = second.driver().inspect(second.config()).await?.expect("lock is held");
+		assert_eq!(info.holder(), second.config().owner());
+		assert_eq!(info.fencing_token(), Some(second.state().fencing_token()));
+		assert!(info.lock_until() - info.locked_at() <= Duration::seconds(10));
+		second.release().await?;
+		assert!(second.driver().inspect(second.config()).await?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_etcd_blocking() -> LockResult<()> {
+		use dist_lock::core::DistLock;
+		let runtime = tokio::runtime::Runtime::new().expect("runtime starts");
+		let client = runtime.block_on(client())?;
+		let driver =
+			|name: &str| EtcdDriver::new(name, &client).with_runtime(runtime.handle().clone());
+		let first = DistLock::new(config("etcd_blocking", 0, 10), driver("etcd_blocking"));
+		let second = DistLock::new(config("etcd_blocking", 0, 10), driver("etcd_blocking"));
+
+		assert!(first.acquire()?);
+		assert!(!second.acquire()?);
+		assert!(first.extend()?);
+		first.release()?;
+		assert!(second.acquire()?);
+		second.release()?;
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn test_etcd_sub_second_lease() -> LockResult<()> {
+		use dist_lock::error::ConfigError;
+		let client = client().await?;
+		let config = LockConfig::new(
+			"etcd_sub_second".to_string(),
+			Duration::zero(),
+			Duration::milliseconds(500),
+		);
+		let dist_lock = AsyncDistLock::new(config, EtcdDriver::new("etcd_sub_second", &client));
+
+		let result = dist_lock.acquire().await;
+		assert!(matches!(
+			result,
+			Err(LockError::InvalidConfig(ConfigError::SubSecondLease(lease))) if lease == Duration::milliseconds(500)
+		));
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn test_etcd_at_least() -> LockResult<()> {
+		let client = client().await?;
+		let holder = AsyncDistLock::new(
+			config("etcd_at_least", 2, 10),
+			EtcdDriver::new("etcd_at_least", &client),
+		);
+		let other = AsyncDistLock::new(
+			config("etcd_at_least", 2, 10),
+			EtcdDriver::new("etcd_at_least", &client),
+		);
+
+		assert!(holder.acquire().await?);
+		holder.release().await?;
+		assert!(!other.acquire().await?);
+		tokio::time::sleep(core::time::Duration::from_secs(3)).await;
+		assert!(other.acquire().await?);
+		other.release().await?;
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn test_etcd_reentrant() -> LockResult<()> {
+		let client = client().await?;
+		let config = config("etcd_reentrant", 0, 10).with_reentrant(true);
+		let other = AsyncDistLock::new(config.clone(), EtcdDriver::new("etcd_reentrant", &client));
+		let config = config.with_owner(LockOwner::new());
+		let outer = AsyncDistLock::new(config.clone(), EtcdDriver::new("etcd_reentrant", &client));
+		let inner = AsyncDistLock::new(config, EtcdDriver::new("etcd_reentrant", &client));
+
+		assert!(outer.acquire().await?);
+		// same thread but no shared owner
+		assert!(!other.acquire().await?);
+		assert!(inner.acquire().await?);
+		assert_eq!(inner.state().holds(), 2);
+		inner.release().await?;
+		assert!(outer.driver().inspect(outer.config()).await?.is_some());
+		outer.release().await?;
+		assert!(outer.driver().inspect(outer.config()).await?.is_none());
+		Ok(())
+	}
+
+	#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+	async fn test_etcd_admin() -> LockResult<()> {
+		let client = client().await?;
+		let held =
+			AsyncDistLock::new(config("etcd_admin", 0, 10), EtcdDriver::new("etcd_admin", &client));
+		let admin = EtcdDriver::new("admin", &client);
+
+		assert!(held.acquire().await?);
+		let locks = admin.list_locks().await?;
+		let info = locks.iter().find(|info| info.name() == "etcd_admin").expect("lock is listed");
+		assert_eq!(info.fencing_token(), Some(held.state().fencing_token()));
+
+		assert!(admin.force_unlock("etcd_admin").await?);
+		assert!(!admin.force_unlock("etcd_admin").await?);
+		assert!(!held.extend().await?);
+		Ok(())
+	}
+}
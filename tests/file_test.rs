@@ -0,0 +1,183 @@
+#[cfg(feature = "file")]
+mod file {
+	use chrono::Duration;
+	use chrono::Utc;
+	use dist_lock::admin::LockAdmin;
+	use dist_lock::clock::MockClock;
+	use dist_lock::core::DistLock;
+	use dist_lock::core::LockConfig;
+	use dist_lock::core::Lockable;
+	use dist_lock::error::LockError;
+	use dist_lock::error::LockResult;
+	use dist_lock::provider::FileDriver;
+	use dist_lock::retry::RetryPolicy;
+	use std::fs;
+	use std::sync::atomic::AtomicU32;
+	use std::sync::atomic::Ordering;
+	use std::sync::Arc;
+	use std::thread;
+
+	/// A driver on an empty directory of its own.
+	fn driver(test: &str) -> LockResult<FileDriver> {
+		let dir = std::env::temp_dir().join("dist_lock_file_test").join(test);
+		let _ = fs::remove_dir_all(&dir);
+		FileDriver::new(dir)
+	}
+
+	fn config(name: &str, min_lock: i64, max_lock: i64) -> LockConfig {
+		LockConfig::from_mills(name.to_string(), min_lock, max_lock)
+	}
+
+	#[test]
+	fn test_file_lock() -> LockResult<()> {
+		let driver = driver("lock")?;
+		let first = DistLock::new(config("file:lock/a", 0, 10_000), driver.clone());
+		let second = DistLock::new(config("file:lock/a", 0, 10_000), driver.clone());
+
+		assert!(first.acquire()?);
+		assert!(driver.dir().join("file%3Alock%2Fa.lock").exists());
+		assert!(!second.acquire()?);
+		let token = first.state().fencing_token();
+		first.release()?;
+		assert!(second.acquire()?);
+		assert!(second.state().fencing_token() > token);
+
+		let info = driver.inspect(second.config())?.expect("lock is held");
+		assert_eq!(info.name(), "file:lock/a");
+		assert_eq!(info.holder(), second.config().owner());
+		assert_eq!(info.fencing_token(), Some(second.state().fencing_token()));
+		second.release()?;
+		assert!(driver.inspect(second.config())?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_file_expiry_and_at_least() -> LockResult<()> {
+		let clock = MockClock::new(Utc::now());
+		let driver = driver("expiry")?;
+		let config = config("file_expiry", 200, 1_000).with_clock(clock.clone());
+		let holder = DistLock::new(config.clone(), driver.clone());
+		let other = DistLock::new(config, driver);
+
+		assert!(holder.acquire()?);
+		clock.advance(Duration::milliseconds(600));
+		assert!(holder.extend()?);
+		clock.advance(Duration::milliseconds(600));
+		// extended, so still held after the first max_lock
+		assert!(!other.acquire()?);
+		clock.advance(Duration::milliseconds(600));
+		assert!(!holder.extend()?);
+		assert!(other.acquire()?);
+
+		// kept until min_lock has passed
+		other.release()?;
+		assert!(!holder.acquire()?);
+		clock.advance(Duration::milliseconds(200));
+		assert!(holder.acquire()?);
+		holder.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_file_threads() -> LockResult<()> {
+		let dir = driver("threads")?.dir().to_path_buf();
+		let config = LockConfig::builder("file_threads")
+			.max_lock(Duration::seconds(10))
+			.retry_policy(RetryPolicy::fixed(Duration::milliseconds(1)))
+			.build()?;
+		let inside = Arc::new(AtomicU32::new(0));
+		let workers = (0..4)
+			.map(|_| -> LockResult<_> {
+				// a driver of its own, like another process would have
+				let lock = DistLock::new(config.clone(), FileDriver::new(&dir)?);
+				let inside = inside.clone();
+				Ok(thread::spawn(move || -> LockResult<()> {
+					for _ in 0..10 {
+						let _guard = lock.lock()?;
+						assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+						inside.fetch_sub(1, Ordering::SeqCst);
+					}
+					Ok(())
+				}))
+			})
+			.collect::<LockResult<Vec<_>>>()?;
+
+		workers.into_iter().try_for_each(|worker| worker.join().unwrap())
+	}
+
+	#[test]
+	fn test_file_admin() -> LockResult<()> {
+		let clock = MockClock::new(Utc::now());
+		let driver = driver("admin")?.with_clock(clock.clone());
+		let held = DistLock::new(
+			config("file_admin_held", 0, 1_000).with_clock(clock.clone()),
+			driver.clone(),
+		);
+		let expired = DistLock::new(
+			config("file_admin_expired", 0, 500).with_clock(clock.clone()),
+			driver.clone(),
+		);
+
+		assert!(held.acquire()?);
+		assert!(expired.acquire()?);
+		assert_eq!(driver.list_locks()?.len(), 2);
+		clock.advance(Duration::milliseconds(500));
+		let locks = driver.list_locks()?;
+		assert_eq!(locks.len(), 1);
+		assert_eq!(locks[0].name(), "file_admin_held");
+
+		assert!(driver.force_unlock("file_admin_held")?);
+		assert!(!driver.force_unlock("file_admin_held")?);
+		assert!(!driver.force_unlock("file_admin_missing")?);
+		assert!(driver.list_locks()?.is_empty());
+		assert!(!held.extend()?);
+		Ok(())
+	}
+
+	#[test]
+	fn test_file_escaped_values() -> LockResult<()> {
+		let driver = driver("escaped")?;
+		let config = LockConfig::builder("file_escaped\nname=1")
+			.owner("worker\nowner=%41")
+			.max_lock(Duration::seconds(10))
+			.build()?;
+		let lock = DistLock::new(config.clone(), driver.clone());
+
+		assert!(lock.acquire()?);
+		let info = driver.inspect(&config)?.expect("lock is held");
+		assert_eq!(info.name(), "file_escaped\nname=1");
+		assert_eq!(info.holder().id(), "worker\nowner=%41");
+		assert_eq!(driver.list_locks()?.len(), 1);
+		assert!(lock.extend()?);
+		lock.release()?;
+		assert!(driver.inspect(&config)?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_file_replaced_whole() -> LockResult<()> {
+		let driver = driver("replaced")?;
+		let lock = DistLock::new(config("file_replaced", 0, 10_000), driver.clone());
+
+		assert!(lock.acquire()?);
+		let token = lock.state().fencing_token();
+		lock.release()?;
+		// a temporary file left by a crash before the rename is ignored and overwritten
+		fs::write(driver.dir().join("file_replaced.tmp"), "name=file_replaced\nowner\n")?;
+		assert!(driver.list_locks()?.is_empty());
+		assert!(lock.acquire()?);
+		assert!(lock.state().fencing_token() > token);
+		assert!(!driver.dir().join("file_replaced.tmp").exists());
+		lock.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_file_invalid() -> LockResult<()> {
+		let driver = driver("invalid")?;
+		fs::write(driver.dir().join("file_invalid.lock"), "name=file_invalid\nowner\n")?;
+		let lock = DistLock::new(config("file_invalid", 0, 1_000), driver);
+		assert!(matches!(lock.acquire(), Err(LockError::InvalidLock(_))));
+		Ok(())
+	}
+}
@@ -15,7 +15,13 @@ mod redis_macro {
 		test_macro().await
 	}
 
-	#[dist_lock(name = "random_lock", at_most = "10s", at_least="6s", transport(create_redis_conn()?))]
+	#[dist_lock(
+		name = "random_lock",
+		at_most = "10s",
+		at_least = "6s",
+		provider = "redis",
+		transport(create_redis_conn()?)
+	)]
 	pub async fn test_macro() -> LockResult<()> {
 		println!("{:?}", random_lock.state());
 		Ok(())
@@ -39,7 +45,13 @@ mod diesel_macro {
 		Ok(test_macro()?)
 	}
 
-	#[dist_lock(name = "test_lock", at_most = "15s", at_least="10s", transport(create_mysql_conn()?))]
+	#[dist_lock(
+		name = "test_lock",
+		at_most = "15s",
+		at_least = "10s",
+		provider = "diesel",
+		transport(create_mysql_conn()?)
+	)]
 	fn test_macro() -> LockResult<()> {
 		Ok(())
 	}
@@ -72,6 +84,7 @@ mod zk_macro {
 		name = "test_zk_macro",
 		at_most = "15s",
 		at_least = "10s",
+		provider = "zookeeper",
 		transport(create_zk_conn()?)
 	)]
 	fn test_zk_macro() -> LockResult<()> {
@@ -93,3 +106,68 @@ mod zk_macro {
 		}
 	}
 }
+
+#[cfg(feature = "memory")]
+mod memory_macro {
+
+	use dist_lock::core::LockConfig;
+	use dist_lock::core::Lockable;
+	use dist_lock::dist_lock;
+	use dist_lock::error::LockResult;
+	use dist_lock::provider::MemoryDriver;
+	use std::sync::OnceLock;
+
+	static DRIVER: OnceLock<MemoryDriver> = OnceLock::new();
+
+	#[test]
+	fn test_memory_lock_macro() -> LockResult<()> {
+		test_macro()?;
+		let config = LockConfig::from_mills("memory_macro_lock".to_string(), 0, 10_000);
+		assert!(driver().inspect(&config)?.is_none());
+		Ok(())
+	}
+
+	#[dist_lock(
+		name = "memory_macro_lock",
+		at_most = "10s",
+		provider = "memory",
+		transport(driver())
+	)]
+	pub fn test_macro() -> LockResult<()> {
+		assert!(memory_macro_lock.state().is_locked());
+		Ok(())
+	}
+
+	fn driver<'a>() -> &'a MemoryDriver {
+		DRIVER.get_or_init(MemoryDriver::new)
+	}
+}
+
+#[cfg(feature = "file")]
+mod file_macro {
+
+	use dist_lock::core::LockConfig;
+	use dist_lock::core::Lockable;
+	use dist_lock::dist_lock;
+	use dist_lock::error::LockResult;
+	use dist_lock::provider::FileDriver;
+	use std::path::PathBuf;
+
+	#[test]
+	fn test_file_lock_macro() -> LockResult<()> {
+		test_macro()?;
+		let config = LockConfig::from_mills("file_macro_lock".to_string(), 0, 10_000);
+		assert!(FileDriver::new(dir())?.inspect(&config)?.is_none());
+		Ok(())
+	}
+
+	#[dist_lock(name = "file_macro_lock", at_most = "10s", provider = "file", transport(dir()))]
+	pub fn test_macro() -> LockResult<()> {
+		assert!(file_macro_lock.state().is_locked());
+		Ok(())
+	}
+
+	fn dir() -> PathBuf {
+		std::env::temp_dir().join("dist_lock_macro_test")
+	}
+}
@@ -0,0 +1,231 @@
+#[cfg(feature = "memory")]
+mod memory {
+	use chrono::Duration;
+	use chrono::Utc;
+	use dist_lock::admin::LockAdmin;
+	use dist_lock::clock::MockClock;
+	use dist_lock::core::DistLock;
+	use dist_lock::core::LockConfig;
+	use dist_lock::core::LockState;
+	use dist_lock::core::Lockable;
+	use dist_lock::error::LockError;
+	use dist_lock::error::LockResult;
+	use dist_lock::provider::MemoryDriver;
+	use dist_lock::provider::QuorumDriver;
+	use dist_lock::retry::RetryPolicy;
+	use std::sync::atomic::AtomicU32;
+	use std::sync::atomic::Ordering;
+	use std::sync::Arc;
+	use std::thread;
+
+	fn config(name: &str, min_lock: i64, max_lock: i64) -> LockConfig {
+		LockConfig::from_mills(name.to_string(), min_lock, max_lock)
+	}
+
+	#[test]
+	fn test_memory_lock() -> LockResult<()> {
+		let driver = MemoryDriver::new();
+		let first = DistLock::new(config("memory_lock", 0, 10_000), driver.clone());
+		let second = DistLock::new(config("memory_lock", 0, 10_000), driver.clone());
+
+		assert!(first.acquire()?);
+		assert!(!second.acquire()?);
+		let token = first.state().fencing_token();
+		first.release()?;
+		assert!(second.acquire()?);
+		assert!(second.state().fencing_token() > token);
+
+		let info = driver.inspect(second.config())?.expect("lock is held");
+		assert_eq!(info.holder(), second.config().owner());
+		assert_eq!(info.fencing_token(), Some(second.state().fencing_token()));
+		second.release()?;
+		assert!(driver.inspect(second.config())?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_memory_expiry_and_extend() -> LockResult<()> {
+		let clock = MockClock::new(Utc::now());
+		let driver = MemoryDriver::new();
+		let config = config("memory_expiry", 0, 200).with_clock(clock.clone());
+		let holder = DistLock::new(config.clone(), driver.clone());
+		let other = DistLock::new(config, driver);
+
+		assert!(holder.acquire()?);
+		clock.advance(Duration::milliseconds(120));
+		assert!(holder.extend()?);
+		clock.advance(Duration::milliseconds(120));
+		// extended, so still held after the first max_lock
+		assert!(holder.state().check_locked(holder.config()));
+		assert!(!other.acquire()?);
+		clock.advance(Duration::milliseconds(120));
+		assert!(!holder.state().check_locked(holder.config()));
+		assert!(!holder.extend()?);
+		assert!(other.acquire()?);
+		other.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_memory_at_least() -> LockResult<()> {
+		let clock = MockClock::new(Utc::now());
+		let driver = MemoryDriver::new();
+		let config = config("memory_at_least", 200, 10_000).with_clock(clock.clone());
+		let holder = DistLock::new(config.clone(), driver.clone());
+		let other = DistLock::new(config, driver);
+
+		assert!(holder.acquire()?);
+		clock.advance(Duration::milliseconds(50));
+		holder.release()?;
+		assert!(!other.acquire()?);
+		clock.advance(Duration::milliseconds(100));
+		assert!(!other.acquire()?);
+		clock.advance(Duration::milliseconds(50));
+		assert!(other.acquire()?);
+		other.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_memory_reentrant() -> LockResult<()> {
+		let driver = MemoryDriver::new();
+		let config = config("memory_reentrant", 0, 10_000).with_reentrant(true);
+		let outer = DistLock::new(config.clone(), driver.clone());
+		let inner = DistLock::new(config, driver.clone());
+
+		assert!(outer.acquire()?);
+		assert!(inner.acquire()?);
+		assert_eq!(inner.state().holds(), 2);
+		inner.release()?;
+		assert!(driver.inspect(outer.config())?.is_some());
+		outer.release()?;
+		assert!(driver.inspect(outer.config())?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_memory_nested_guards() -> LockResult<()> {
+		let driver = MemoryDriver::new();
+		let config = config("memory_nested_guards", 0, 10_000).with_reentrant(true);
+		let lock = DistLock::new(config, driver.clone());
+
+		let outer = lock.lock()?;
+		let inner = lock.lock()?;
+		assert_eq!(inner.state().holds(), 2);
+		inner.release()?;
+		assert_eq!(outer.state().holds(), 1);
+		assert!(driver.inspect(lock.config())?.is_some());
+		drop(outer);
+		assert!(driver.inspect(lock.config())?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_memory_threads() -> LockResult<()> {
+		let driver = MemoryDriver::new();
+		let config = LockConfig::builder("memory_threads")
+			.max_lock(Duration::seconds(10))
+			.retry_policy(RetryPolicy::fixed(Duration::milliseconds(1)))
+			.build()?;
+		let inside = Arc::new(AtomicU32::new(0));
+		let workers = (0..4)
+			.map(|_| {
+				let lock = DistLock::new(config.clone(), driver.clone());
+				let inside = inside.clone();
+				thread::spawn(move || -> LockResult<()> {
+					for _ in 0..20 {
+						let _guard = lock.lock()?;
+						assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+						inside.fetch_sub(1, Ordering::SeqCst);
+					}
+					Ok(())
+				})
+			})
+			.collect::<Vec<_>>();
+
+		workers.into_iter().try_for_each(|worker| worker.join().unwrap())
+	}
+
+	#[test]
+	fn test_memory_retry_timeout_on_clock() -> LockResult<()> {
+		let clock = MockClock::new(Utc::now());
+		let driver = MemoryDriver::new();
+		let config = config("memory_retry_clock", 0, 3_600_000).with_clock(clock);
+		let holder = DistLock::new(config.clone(), driver.clone());
+		let waiter = DistLock::new(config, driver);
+
+		assert!(holder.acquire()?);
+		// the mock clock stands still, the timeout passes in real time anyway
+		let policy =
+			RetryPolicy::fixed(Duration::milliseconds(1)).timeout(Duration::milliseconds(50));
+		assert!(matches!(waiter.acquire_with(policy), Err(LockError::Timeout)));
+		Ok(())
+	}
+
+	/// Applies every call but may answer the acquire with a timeout, like a slow backend.
+	#[derive(Debug)]
+	struct Flaky {
+		driver: MemoryDriver,
+		time_out: bool,
+	}
+
+	impl Lockable for Flaky {
+		fn acquire_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+			let state = self.driver.acquire_lock(config)?;
+			match self.time_out {
+				true => Err(LockError::Timeout),
+				false => Ok(state),
+			}
+		}
+
+		fn release_lock(&self, config: &LockConfig, state: &LockState) -> LockResult<LockState> {
+			self.driver.release_lock(config, state)
+		}
+
+		fn extend_lock(&self, config: &LockConfig) -> LockResult<LockState> {
+			self.driver.extend_lock(config)
+		}
+	}
+
+	#[test]
+	fn test_memory_quorum_rollback() -> LockResult<()> {
+		let drivers = [MemoryDriver::new(), MemoryDriver::new(), MemoryDriver::new()];
+		let flaky = |time_out: bool| {
+			drivers
+				.iter()
+				.enumerate()
+				.map(|(i, driver)| Flaky { driver: driver.clone(), time_out: time_out && i == 1 })
+				.collect::<Vec<_>>()
+		};
+		let config = config("memory_quorum_rollback", 0, 10_000);
+		let other = DistLock::new(config.clone(), drivers[2].clone());
+		let quorum = DistLock::new(config.clone(), QuorumDriver::new(flaky(true)));
+
+		assert!(other.acquire()?);
+		assert!(!quorum.acquire()?);
+		// the lock written before the timeout is released with the rest of the minority
+		assert!(drivers[0].inspect(&config)?.is_none());
+		assert!(drivers[1].inspect(&config)?.is_none());
+		other.release()?;
+		let quorum = DistLock::new(config, QuorumDriver::new(flaky(false)));
+		assert!(quorum.acquire()?);
+		quorum.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_memory_admin() -> LockResult<()> {
+		let driver = MemoryDriver::new();
+		let held = DistLock::new(config("memory_admin", 0, 10_000), driver.clone());
+
+		assert!(held.acquire()?);
+		let locks = driver.list_locks()?;
+		assert_eq!(locks.len(), 1);
+		assert_eq!(locks[0].name(), "memory_admin");
+
+		assert!(driver.force_unlock("memory_admin")?);
+		assert!(driver.list_locks()?.is_empty());
+		assert!(!held.extend()?);
+		Ok(())
+	}
+}
@@ -5,8 +5,14 @@ mod redis {
 	use std::time::Instant;
 
 	use chrono::Duration;
+	#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+	use dist_lock::core::AsyncDistLock;
+	#[cfg(any(feature = "redis_tokio", feature = "redis_async_std"))]
+	use dist_lock::core::AsyncLockable;
+	#[cfg(feature = "redis_common")]
 	use dist_lock::core::DistLock;
 	use dist_lock::core::LockConfig;
+	#[cfg(feature = "redis_common")]
 	use dist_lock::core::Lockable;
 	use dist_lock::error::LockResult;
 	use dist_lock::provider::redis::RedisDriver;
@@ -23,6 +29,214 @@ mod redis {
 		check_lock(&dist_lock)
 	}
 
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_not_owner() -> LockResult<()> {
+		use dist_lock::error::LockError;
+		let lock_name = "owner_lock".to_string();
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		let holder = DistLock::new(config.clone(), RedisDriver::new(&lock_name, &client));
+		let other = DistLock::new(config, RedisDriver::new(&lock_name, &client));
+
+		assert!(holder.acquire()?);
+		assert!(!other.acquire()?);
+		let result = Lockable::release_lock(other.driver(), other.config(), &holder.state());
+		assert!(matches!(result, Err(LockError::NotOwner(_))));
+		let result = Lockable::extend_lock(other.driver(), other.config());
+		assert!(matches!(result, Err(LockError::NotOwner(_))));
+		assert!(holder.extend()?);
+		holder.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_rw_lock() -> LockResult<()> {
+		use dist_lock::rwlock::DistRwLock;
+		let lock_name = "rw_lock".to_string();
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		let rw_lock = || DistRwLock::new(config.clone(), RedisDriver::new(&lock_name, &client));
+		let reader = rw_lock();
+		let other_reader = rw_lock();
+		let writer = rw_lock();
+
+		let read = reader.read()?.expect("no writer");
+		let other_read = other_reader.read()?.expect("readers share the lock");
+		assert!(writer.write()?.is_none());
+		drop(read);
+		assert!(writer.write()?.is_none());
+		drop(other_read);
+
+		let write = writer.write()?.expect("readers are gone");
+		assert!(reader.read()?.is_none());
+		assert!(write.extend()?);
+		write.release()?;
+		let _read = reader.read()?.expect("writer is gone");
+		Ok(())
+	}
+
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_semaphore() -> LockResult<()> {
+		use dist_lock::semaphore::DistSemaphore;
+		let lock_name = "semaphore".to_string();
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(1));
+		let semaphore =
+			|| DistSemaphore::new(config.clone(), 2, RedisDriver::new(&lock_name, &client));
+		let first = semaphore();
+		let second = semaphore();
+		let third = semaphore();
+
+		let permit = first.try_permit()?.expect("2 permits left");
+		assert!(second.acquire()?);
+		assert!(third.try_permit()?.is_none());
+		drop(permit);
+		let permit = third.try_permit()?.expect("a permit was given back");
+		assert!(permit.extend()?);
+
+		// the permit of second is never released and expires, third keeps renewing
+		std::mem::forget(second);
+		std::thread::sleep(core::time::Duration::from_millis(600));
+		assert!(permit.extend()?);
+		std::thread::sleep(core::time::Duration::from_millis(600));
+		assert!(first.acquire()?);
+		assert!(permit.extend()?);
+		first.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_fair() -> LockResult<()> {
+		use dist_lock::error::LockError;
+		let lock_name = "fair_lock".to_string();
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10))
+				.with_fair(Duration::seconds(5));
+		let lock = || DistLock::new(config.clone(), RedisDriver::new(&lock_name, &client));
+		let holder = lock();
+		let first = lock();
+		let second = lock();
+		let quitter = lock();
+
+		assert!(holder.acquire()?);
+		let result = quitter.acquire_timeout(Duration::milliseconds(200));
+		assert!(matches!(result, Err(LockError::Timeout)));
+		assert!(!first.acquire()?);
+		assert!(!second.acquire()?);
+		holder.release()?;
+
+		// `quitter` left the queue, the lock is free but `first` is ahead of `second`
+		assert!(!second.acquire()?);
+		assert!(first.acquire()?);
+		first.release()?;
+		assert!(second.acquire()?);
+		second.release()?;
+		Ok(())
+	}
+
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_inspect() -> LockResult<()> {
+		use dist_lock::core::LockOwner;
+		let lock_name = "inspect_lock".to_string();
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let config = LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(5))
+			.with_owner(LockOwner::from_id("inspected"));
+		let dist_lock = DistLock::new(config, RedisDriver::new(&lock_name, &client));
+
+		assert!(Lockable::inspect(dist_lock.driver(), dist_lock.config())?.is_none());
+		assert!(dist_lock.acquire()?);
+		let info =
+			Lockable::inspect(dist_lock.driver(), dist_lock.config())?.expect("lock is held");
+		assert_eq!(info.name(), "inspect_lock");
+		assert_eq!(info.holder().id(), "inspected");
+		assert_eq!(info.fencing_token(), Some(dist_lock.state().fencing_token()));
+		assert!(info.lock_until() - info.locked_at() <= Duration::seconds(5));
+		assert_eq!(info.holds(), 1);
+
+		dist_lock.release()?;
+		assert!(Lockable::inspect(dist_lock.driver(), dist_lock.config())?.is_none());
+		Ok(())
+	}
+
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_force_unlock() -> LockResult<()> {
+		use chrono::Utc;
+		use dist_lock::admin::LockAdmin;
+		use dist_lock::clock::Clock;
+		use dist_lock::clock::MockClock;
+		use dist_lock::rwlock::DistRwLock;
+		let lock_name = "admin_lock".to_string();
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let config =
+			LockConfig::new(lock_name.clone(), Duration::seconds(0), Duration::seconds(10));
+		let holder = DistLock::new(config.clone(), RedisDriver::new(&lock_name, &client));
+		let writer = DistRwLock::new(config.clone(), RedisDriver::new(&lock_name, &client));
+		let clock = MockClock::new(Utc::now() + Duration::days(1));
+		let admin = RedisDriver::new("admin", &client).with_clock(clock.clone());
+
+		assert!(holder.acquire()?);
+		let _write = writer.write()?.expect("no readers");
+		let locks = admin.list_locks()?;
+		let info = locks.iter().find(|info| info.name() == lock_name).expect("lock is listed");
+		// the remaining time is added to the clock of the admin driver
+		assert!(info.lock_until() > clock.now());
+
+		assert!(admin.force_unlock(&lock_name)?);
+		assert!(!admin.force_unlock(&lock_name)?);
+		let other = DistLock::new(config.clone(), RedisDriver::new(&lock_name, &client));
+		assert!(other.acquire()?);
+		other.release()?;
+		let other_writer = DistRwLock::new(config, RedisDriver::new(&lock_name, &client));
+		assert!(other_writer.write()?.is_some());
+		Ok(())
+	}
+
+	#[cfg(feature = "redis_common")]
+	#[test]
+	fn test_multi_lock() -> LockResult<()> {
+		use dist_lock::multilock::MultiLock;
+		let client = Client::open("redis://127.0.0.1:6379/")?;
+		let single = |name: &str| {
+			let config =
+				LockConfig::new(name.to_string(), Duration::seconds(0), Duration::seconds(10));
+			DistLock::new(config, RedisDriver::new(name, &client))
+		};
+		let config =
+			LockConfig::new("multi_lock".to_string(), Duration::seconds(1), Duration::seconds(10));
+		let names = ["redis_account_2", "redis_account_1"];
+		let multi = MultiLock::new(config, names, RedisDriver::new("multi_lock", &client));
+
+		let held = single(names[0]);
+		assert!(held.acquire()?);
+		assert!(multi.try_lock()?.is_none());
+		let rolled_back = single(names[1]);
+		assert!(rolled_back.acquire()?);
+		rolled_back.release()?;
+		held.release()?;
+
+		let guard = multi.try_lock()?.expect("names are free");
+		assert!(guard.fencing_tokens().iter().all(|token| *token > 0));
+		assert!(!held.acquire()?);
+		assert!(guard.extend()?);
+		guard.release()?;
+		// every name is kept for the at least hold
+		assert!(!held.acquire()? && !rolled_back.acquire()?);
+		std::thread::sleep(core::time::Duration::from_millis(1100));
+		assert!(held.acquire()? && rolled_back.acquire()?);
+		held.release()?;
+		rolled_back.release()?;
+		Ok(())
+	}
+
 	#[cfg(feature = "redis_r2d2")]
 	#[test]
 	fn test_t2d2_lock() -> LockResult<()> {
@@ -42,8 +256,8 @@ mod redis {
 		let client = Client::open("redis://127.0.0.1:6379/")?;
 		let driver = RedisDriver::new(&lock_name, &client);
 		let config = LockConfig::new(lock_name, Duration::seconds(0), Duration::seconds(10));
-		let dist_lock = DistLock::new(config, driver);
-		check_lock(&dist_lock).await
+		let dist_lock = AsyncDistLock::new(config, driver);
+		check_async_lock(&dist_lock).await
 	}
 
 	#[cfg(feature = "redis_async_std")]
@@ -53,12 +267,12 @@ mod redis {
 		let client = Client::open("redis://127.0.0.1:6379/")?;
 		let driver = RedisDriver::new(&lock_name, &client);
 		let config = LockConfig::new(lock_name, Duration::seconds(0), Duration::seconds(10));
-		let dist_lock = DistLock::new(config, driver);
-		check_lock(&dist_lock).await
+		let dist_lock = AsyncDistLock::new(config, driver);
+		check_async_lock(&dist_lock).await
 	}
 
-	#[cfg(feature = "async-std")]
-	async fn check_lock<T: Lockable>(dist_lock: &DistLock<T>) -> LockResult<()> {
+	#[cfg(all(feature = "redis_async_std", not(feature = "redis_tokio")))]
+	async fn check_async_lock<T: AsyncLockable>(dist_lock: &AsyncDistLock<T>) -> LockResult<()> {
 		let now = Instant::now();
 		assert!(dist_lock.acquire().await?);
 		async_std::task::sleep(core::time::Duration::from_secs(5)).await;
@@ -69,8 +283,8 @@ mod redis {
 		Ok(())
 	}
 
-	#[cfg(feature = "tokio")]
-	async fn check_lock<T: Lockable>(dist_lock: &DistLock<T>) -> LockResult<()> {
+	#[cfg(feature = "redis_tokio")]
+	async fn check_async_lock<T: AsyncLockable>(dist_lock: &AsyncDistLock<T>) -> LockResult<()> {
 		let now = Instant::now();
 		assert!(dist_lock.acquire().await?);
 		tokio::time::sleep(core::time::Duration::from_secs(5)).await;
@@ -81,7 +295,7 @@ mod redis {
 		Ok(())
 	}
 
-	#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+	#[cfg(feature = "redis_common")]
 	fn check_lock<T: Lockable>(dist_lock: &DistLock<T>) -> LockResult<()> {
 		use std::thread;
 		let now = Instant::now();
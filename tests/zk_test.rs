@@ -37,6 +37,216 @@ mod zookeeper {
 		Ok(())
 	}
 
+	#[test]
+	fn test_zk_legacy_node() -> LockResult<()> {
+		let zk_client =
+			ZooKeeper::connect("127.0.0.1:2181", core::time::Duration::from_secs(60), MyWatcher)?;
+		let driver = ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client)?;
+		let path = driver.path("zk_legacy_lock");
+		if zk_client.exists(&path, false)?.is_none() {
+			driver.create_zk_path(&path)?;
+		}
+		// a node as written by earlier versions: the lock time in millis and nothing else
+		let locked_at = chrono::Utc::now() - Duration::seconds(1);
+		zk_client.set_data(&path, locked_at.timestamp_millis().to_be_bytes().to_vec(), None)?;
+
+		let config = LockConfig::new(
+			"zk_legacy_lock".to_string(),
+			Duration::seconds(0),
+			Duration::seconds(2),
+		);
+		let dist_lock = DistLock::new(config, driver);
+		// held by the older client until max_lock has passed since its lock time
+		assert!(!dist_lock.acquire()?);
+		std::thread::sleep(core::time::Duration::from_millis(1500));
+		assert!(dist_lock.acquire()?);
+		dist_lock.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_zk_new_lock_race() -> LockResult<()> {
+		let name = format!("zk_race_{}", chrono::Utc::now().timestamp_millis());
+		let winners = (0..4)
+			.map(|_| {
+				let name = name.clone();
+				std::thread::spawn(move || -> LockResult<bool> {
+					let zk_client = ZooKeeper::connect(
+						"127.0.0.1:2181",
+						core::time::Duration::from_secs(60),
+						MyWatcher,
+					)?;
+					let driver = ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client)?;
+					let config = LockConfig::new(name, Duration::seconds(0), Duration::seconds(10));
+					Ok(driver.acquire_lock(&config)?.is_locked())
+				})
+			})
+			.collect::<Vec<_>>()
+			.into_iter()
+			.map(|worker| worker.join().unwrap())
+			.collect::<LockResult<Vec<_>>>()?;
+
+		// every client saw the node missing, only one of them may take it
+		assert_eq!(winners.iter().filter(|locked| **locked).count(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn test_zk_rw_lock() -> LockResult<()> {
+		use dist_lock::rwlock::DistRwLock;
+		let zk_client =
+			ZooKeeper::connect("127.0.0.1:2181", core::time::Duration::from_secs(60), MyWatcher)?;
+		let config =
+			LockConfig::new("zk_rw_lock".to_string(), Duration::seconds(0), Duration::seconds(10));
+		let rw_lock = || -> LockResult<_> {
+			let driver = ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client)?;
+			Ok(DistRwLock::new(config.clone(), driver))
+		};
+		let reader = rw_lock()?;
+		let other_reader = rw_lock()?;
+		let writer = rw_lock()?;
+
+		let read = reader.read()?.expect("no writer");
+		let other_read = other_reader.read()?.expect("readers share the lock");
+		assert!(writer.write()?.is_none());
+		drop(read);
+		assert!(writer.write()?.is_none());
+		drop(other_read);
+
+		let write = writer.write()?.expect("readers are gone");
+		assert!(reader.read()?.is_none());
+		assert!(write.extend()?);
+		write.release()?;
+		let _read = reader.read()?.expect("writer is gone");
+		Ok(())
+	}
+
+	#[test]
+	fn test_zk_semaphore() -> LockResult<()> {
+		use dist_lock::semaphore::DistSemaphore;
+		let zk_client =
+			ZooKeeper::connect("127.0.0.1:2181", core::time::Duration::from_secs(60), MyWatcher)?;
+		let config =
+			LockConfig::new("zk_semaphore".to_string(), Duration::seconds(0), Duration::seconds(1));
+		let semaphore = || -> LockResult<_> {
+			let driver = ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client)?;
+			Ok(DistSemaphore::new(config.clone(), 2, driver))
+		};
+		let first = semaphore()?;
+		let second = semaphore()?;
+		let third = semaphore()?;
+
+		let permit = first.try_permit()?.expect("2 permits left");
+		assert!(second.acquire()?);
+		assert!(third.try_permit()?.is_none());
+		drop(permit);
+		let permit = third.try_permit()?.expect("a permit was given back");
+		assert!(permit.extend()?);
+
+		// the permit of second is never released and expires, third keeps renewing
+		std::mem::forget(second);
+		std::thread::sleep(core::time::Duration::from_millis(600));
+		assert!(permit.extend()?);
+		std::thread::sleep(core::time::Duration::from_millis(600));
+		assert!(first.acquire()?);
+		assert!(permit.extend()?);
+		first.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_zk_fair() -> LockResult<()> {
+		use dist_lock::error::LockError;
+		let zk_client =
+			ZooKeeper::connect("127.0.0.1:2181", core::time::Duration::from_secs(60), MyWatcher)?;
+		let config = LockConfig::new(
+			"zk_fair_lock".to_string(),
+			Duration::seconds(0),
+			Duration::seconds(10),
+		)
+		.with_fair(Duration::seconds(5));
+		let lock = || -> LockResult<_> {
+			let driver = ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client)?;
+			Ok(DistLock::new(config.clone(), driver))
+		};
+		let holder = lock()?;
+		let first = lock()?;
+		let second = lock()?;
+		let quitter = lock()?;
+
+		assert!(holder.acquire()?);
+		let result = quitter.acquire_timeout(Duration::milliseconds(200));
+		assert!(matches!(result, Err(LockError::Timeout)));
+		assert!(!first.acquire()?);
+		assert!(!second.acquire()?);
+		holder.release()?;
+
+		// `quitter` left the `.queue` node, the lock is free but `first` is ahead of `second`
+		assert!(!second.acquire()?);
+		assert!(first.acquire()?);
+		first.release()?;
+		assert!(second.acquire()?);
+		second.release()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_zk_inspect() -> LockResult<()> {
+		use dist_lock::core::LockOwner;
+		let zk_client =
+			ZooKeeper::connect("127.0.0.1:2181", core::time::Duration::from_secs(60), MyWatcher)?;
+		let driver = ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client)?;
+		let config = LockConfig::new(
+			"zk_inspect_lock".to_string(),
+			Duration::seconds(0),
+			Duration::seconds(5),
+		)
+		.with_owner(LockOwner::from_id("inspected"));
+		let dist_lock = DistLock::new(config, driver);
+
+		assert!(dist_lock.driver().inspect(dist_lock.config())?.is_none());
+		assert!(dist_lock.acquire()?);
+		let info = dist_lock.driver().inspect(dist_lock.config())?.expect("lock is held");
+		assert_eq!(info.name(), "zk_inspect_lock");
+		assert_eq!(info.holder().id(), "inspected");
+		assert_eq!(info.fencing_token(), Some(dist_lock.state().fencing_token()));
+		assert_eq!(info.lock_until() - info.locked_at(), Duration::seconds(5));
+		assert_eq!(info.holds(), 1);
+
+		dist_lock.release()?;
+		assert!(dist_lock.driver().inspect(dist_lock.config())?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn test_zk_multi_lock() -> LockResult<()> {
+		use dist_lock::multilock::MultiLock;
+		let zk_client =
+			ZooKeeper::connect("127.0.0.1:2181", core::time::Duration::from_secs(60), MyWatcher)?;
+		let driver = || ZookeeperDriver::new(Some("/parent".to_owned()), &zk_client);
+		let config = |name: &str| {
+			LockConfig::new(name.to_string(), Duration::seconds(0), Duration::seconds(10))
+		};
+		let names = ["zk_account_2", "zk_account_1"];
+		let multi = MultiLock::new(config("zk_multi_lock"), names, driver()?);
+
+		let held = DistLock::new(config(names[0]), driver()?);
+		assert!(held.acquire()?);
+		assert!(multi.try_lock()?.is_none());
+		let rolled_back = DistLock::new(config(names[1]), driver()?);
+		assert!(rolled_back.acquire()?);
+		rolled_back.release()?;
+		held.release()?;
+
+		let guard = multi.try_lock()?.expect("names are free");
+		assert!(!held.acquire()? && !rolled_back.acquire()?);
+		assert!(guard.extend()?);
+		drop(guard);
+		assert!(held.acquire()?);
+		held.release()?;
+		Ok(())
+	}
+
 	#[test]
 	fn test_zk_connected() -> LockResult<()> {
 		use core::time::Duration;